@@ -1,38 +1,153 @@
 use anyhow::Result;
+use solana_client::client_error::ClientErrorKind;
 use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_sdk::{pubkey::Pubkey /*, transaction::VersionedTransaction */};
+use solana_client::rpc_request::RpcError;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::{pubkey::Pubkey, signature::Signature, transaction::VersionedTransaction};
 use std::str::FromStr;
 use std::sync::Arc;
-use solana_sdk::{signer::keypair::Keypair, signer::Signer};
-use crate::{Config, get_sol_balance};
+use std::time::{Duration, Instant};
+use solana_sdk::signer::Signer;
+use crate::{Config, Services, get_sol_balance};
+use crate::profit_db::ProfitDatabase;
+use crate::wallets::Wallet;
+use crate::coordinator::TradeCoordinator;
+use crate::lists::Verdict;
 
-pub async fn execute(mint: Pubkey, cfg: Config, payer: Arc<Keypair>) -> Result<()> {
-    let rpc = RpcClient::new(cfg.rpc_http.clone());
-    tracing::info!("🎯 Signal received: attempting to buy {} with {} SOL", mint, cfg.amount_sol);
+/// How one wallet takes part in a signal
+#[derive(Debug, Clone, Copy)]
+struct Allocation {
+    /// Fraction of the wallet's sized position to buy
+    share: f64,
+    /// Creator is on the trusted list, so the holder risk checks are skipped
+    skip_risk: bool,
+    /// When the signal arrived; retries stop at `retry.deadline_secs` after it
+    signal_at: Instant,
+}
+
+/// Buy `mint` with the wallet(s) the pool's policy picks for this source
+pub async fn execute(mint: Pubkey, source: String, cfg: Config, services: Services, db: ProfitDatabase) -> Result<()> {
+    let signal_at = Instant::now();
+    // Paused buys only skip new entries; open positions keep their managers
+    if let Err(halt) = services.governor.check().await {
+        tracing::warn!("⏸️ Ignoring signal for {} from {}: {}", mint, source, halt);
+        return Ok(());
+    }
+    // Held until every wallet's buy is done so a repeated signal can't double up
+    let _mint_lock = match services.coordinator.lock_mint(mint) {
+        Ok(lock) => lock,
+        Err(reason) => {
+            tracing::info!("⏳ Ignoring signal for {} from {}: {}", mint, source, reason);
+            return Ok(());
+        }
+    };
     
-    // Check current balance before trade
-    match get_sol_balance(&cfg.rpc_http, &payer.pubkey()).await {
-        Ok(balance) => {
-            tracing::info!("💰 Current balance before trade: {:.4} SOL", balance);
-            if balance < cfg.amount_sol {
-                let msg = format!("❌ Insufficient balance: {:.4} SOL < {} SOL needed", balance, cfg.amount_sol);
-                tracing::error!("{}", msg);
+    // Block and trust lists, looking up the deployer only when a creator list could match
+    let creator = if services.lists.needs_creator() {
+        let rpc = RpcClient::new(cfg.rpc_http.clone());
+        let lookup = crate::risk::find_creator(&rpc, &mint)
+            .await
+            .and_then(|creator| creator.ok_or_else(|| anyhow::anyhow!("no creation transaction found")));
+        match lookup {
+            Ok(creator) => Some(creator),
+            // Not knowing the deployer mustn't let a blocked one through
+            Err(e) if services.lists.blocks_creators() => {
+                let msg = format!("🚫 Skipping {}: could not find its deployer to check the block list: {}", mint, e);
+                tracing::warn!("{}", msg);
                 crate::notifier::log(msg).await;
                 return Ok(());
             }
+            Err(e) => {
+                tracing::warn!("Could not find the deployer of {}: {}", mint, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let trusted = match services.lists.verdict(&mint, creator.as_ref()) {
+        Verdict::Blocked(reason) => {
+            let msg = format!("🚫 Skipping {}: {}", mint, reason);
+            tracing::warn!("{}", msg);
+            crate::notifier::log(msg).await;
+            return Ok(());
+        }
+        Verdict::Trusted => {
+            tracing::info!("🤝 {} was deployed by trusted creator {}", mint, creator.unwrap_or_default());
+            true
+        }
+        Verdict::Neutral => false,
+    };
+    
+    let selected = services.wallets.select(&source);
+    tracing::info!("🎯 Signal received from {}: attempting to buy {} with {}", source, mint,
+        selected.iter().map(|w| w.name.as_str()).collect::<Vec<_>>().join(", "));
+    // A split buy gives each wallet an equal share of its own sized position
+    let allocation = Allocation {
+        share: 1.0 / selected.len() as f64,
+        skip_risk: trusted && services.lists.config().trusted_skip_risk,
+        signal_at,
+    };
+    let buys = selected.into_iter().map(|wallet| {
+        let name = wallet.name.clone();
+        let buy = execute_with_wallet(mint, source.clone(), cfg.clone(), wallet, allocation, services.coordinator.clone(), db.clone());
+        async move {
+            if let Err(e) = buy.await {
+                tracing::error!("Buy of {} from wallet {} failed: {}", mint, name, e);
+            }
+        }
+    });
+    futures_util::future::join_all(buys).await;
+    Ok(())
+}
+
+async fn execute_with_wallet(
+    mint: Pubkey,
+    source: String,
+    cfg: Config,
+    wallet: Wallet,
+    allocation: Allocation,
+    coordinator: Arc<TradeCoordinator>,
+    db: ProfitDatabase,
+) -> Result<()> {
+    let rpc = RpcClient::new(cfg.rpc_http.clone());
+    let payer = wallet.keypair.clone();
+    
+    // Check current balance before trade and size the position from it
+    let balance = match get_sol_balance(&cfg.rpc_http, &payer.pubkey()).await {
+        Ok(balance) => {
+            tracing::info!("💰 Current balance of {} before trade: {:.4} SOL", wallet.name, balance);
+            balance
         }
         Err(e) => {
             tracing::warn!("Could not check balance before trade: {}", e);
+            crate::notifier::log(format!("❌ Skipping {}: balance unavailable for sizing", mint)).await;
+            return Ok(());
         }
-    }
+    };
+    let mut size_sol = match crate::sizing::position_size(&cfg, &db, &source, balance) {
+        Ok(size) => size * allocation.share,
+        Err(e) if cfg.is_paper() => {
+            tracing::warn!("{} Sizing failed ({}), using amount_sol on paper", crate::paper::TAG, e);
+            cfg.amount_sol * allocation.share
+        }
+        Err(e) => {
+            let msg = format!("❌ Skipping {}: {}", mint, e);
+            tracing::error!("{}", msg);
+            crate::notifier::log(msg).await;
+            return Ok(());
+        }
+    };
+    tracing::info!("💵 Buying {} with {:.4} SOL from {}", mint, size_sol, wallet.name);
     
     // Check token liquidity before attempting to buy
     tracing::info!("Checking liquidity for token {}...", mint);
-    match crate::swap::check_token_liquidity(&mint, cfg.amount_sol).await {
-        Ok(true) => {
+    let probe_out = match crate::swap::check_token_liquidity(&cfg, &mint, size_sol).await {
+        Ok(Some(probe_out)) => {
             tracing::info!("✅ Token {} has sufficient liquidity", mint);
+            Some(probe_out)
         }
-        Ok(false) => {
+        Ok(None) => {
             let msg = format!("❌ Token {} has insufficient liquidity - skipping", mint);
             tracing::warn!("{}", msg);
             crate::notifier::log(msg).await;
@@ -40,63 +155,322 @@ pub async fn execute(mint: Pubkey, cfg: Config, payer: Arc<Keypair>) -> Result<(
         }
         Err(e) => {
             tracing::warn!("Could not check liquidity for {}: {}. Proceeding anyway...", mint, e);
+            None
+        }
+    };
+    
+    // Detect Token-2022 mints so transfer fees are known before we commit
+    let mint_info = match crate::token::fetch_mint(&rpc, &mint).await {
+        Ok(info) => Some(info),
+        Err(e) => {
+            tracing::warn!("Could not fetch mint {}: {}", mint, e);
+            None
+        }
+    };
+    match &mint_info {
+        Some(crate::token::MintInfo { transfer_fee: Some(fee), .. }) => {
+            tracing::warn!("⚠️ {} is a Token-2022 mint with a {} bps transfer fee (max {})",
+                mint, fee.newer.basis_points, fee.newer.maximum_fee);
         }
+        Some(info) if info.is_token_2022() => tracing::info!("{} is a Token-2022 mint", mint),
+        _ => {}
     }
     
-    let sol_mint = Pubkey::from_str("So11111111111111111111111111111111111111112")?;
-    let amount = (cfg.amount_sol * 1e9_f64) as u64;
+    // Holder concentration, creator and bundle checks can veto or shrink the buy
+    let mut risk_note = String::new();
+    if let (true, Some(info)) = (cfg.risk.enabled && !allocation.skip_risk, &mint_info) {
+        match crate::risk::assess(&cfg, &rpc, &mint, info).await {
+            Ok(report) => {
+                match crate::risk::action(&cfg.risk, report.score) {
+                    crate::risk::RiskAction::Block => {
+                        let msg = format!("⚠️ Skipping {}: {}", mint, report);
+                        tracing::warn!("{}", msg);
+                        crate::notifier::log(msg).await;
+                        return Ok(());
+                    }
+                    crate::risk::RiskAction::Shrink(factor) => {
+                        size_sol *= factor;
+                        tracing::info!("📉 Risk score {} shrinks {} to {:.4} SOL", report.score, mint, size_sol);
+                    }
+                    crate::risk::RiskAction::Proceed => {}
+                }
+                risk_note = format!(" | {}", report);
+            }
+            Err(e) => tracing::warn!("Could not assess risk for {}: {}", mint, e),
+        }
+    }
     
-    tracing::info!("Getting swap transaction from Jupiter...");
-    let mut tx = match crate::swap::get_swap_transaction(&cfg, &payer.pubkey(), sol_mint, mint, amount).await {
-        Ok(tx) => tx,
-        Err(e) => {
-            let msg = format!("❌ Failed to get swap route for {}: {}", mint, e);
-            tracing::error!("{}", msg);
+    // Wait for (or be refused) a slot under the concurrency and exposure limits
+    let permit = match coordinator.admit(&wallet.name, mint, size_sol).await {
+        Ok(permit) => permit,
+        Err(reason) => {
+            let msg = format!("⏳ Skipping {} on {}: {}", mint, wallet.name, reason);
+            tracing::warn!("{}", msg);
             crate::notifier::log(msg).await;
-            return Err(e);
+            return Ok(());
         }
     };
-    let bh = rpc.get_latest_blockhash().await?;
-    let mut message = tx.message.clone();
-    match &mut message {
-        solana_sdk::message::VersionedMessage::Legacy(ref mut msg) => {
-            msg.recent_blockhash = bh;
-        }
-        solana_sdk::message::VersionedMessage::V0(ref mut msg) => {
-            msg.recent_blockhash = bh;
+    let (in_flight, open, at_risk) = coordinator.snapshot();
+    tracing::info!("🎟️ Buy slot for {} on {}: {} in flight, {} open, {:.3} SOL at risk", mint, wallet.name, in_flight, open, at_risk);
+    
+    let amount = (size_sol * 1e9_f64) as u64;
+    
+    // Make sure we could get back out before getting in
+    if cfg.sellability.enabled {
+        match crate::sellability::check(&cfg, &rpc, &payer.pubkey(), &mint, mint_info.as_ref(), amount).await {
+            Ok(loss_pct) => tracing::info!("✅ {} is sellable ({:.2}% round trip loss)", mint, loss_pct),
+            Err(reason) => {
+                let msg = format!("🍯 Skipping {}: looks unsellable, {}", mint, reason);
+                tracing::warn!("{}", msg);
+                crate::notifier::log(msg).await;
+                return Ok(());
+            }
         }
     }
-    let message_hash = message.hash();
-    let sig = payer.sign_message(message_hash.as_ref());
-    tx.message = message;
-    tx.signatures = vec![sig];
-    
-    tracing::info!("Sending transaction to buy {}...", mint);
-    match rpc.send_and_confirm_transaction(&tx).await {
-        Ok(signature) => {
-            tracing::info!("✅ Successfully bought {}, signature: {}", mint, signature);
-            
-            // Check balance after successful trade
-            match get_sol_balance(&cfg.rpc_http, &payer.pubkey()).await {
-                Ok(new_balance) => {
-                    tracing::info!("💰 Balance after trade: {:.4} SOL", new_balance);
-                    let _spent_amount = cfg.amount_sol; // Approximate, actual might vary due to fees
-                    crate::notifier::log(format!("🟢 BOUGHT {} | TX: {} | Balance: {:.4} SOL", mint, signature, new_balance)).await;
-                }
-                Err(e) => {
-                    tracing::warn!("Could not check balance after trade: {}", e);
-                    crate::notifier::log(format!("🟢 BOUGHT {} - TX: {}", mint, signature)).await;
+    
+    let sol_mint = Pubkey::from_str("So11111111111111111111111111111111111111112")?;
+    // The tiny liquidity probe barely moves the price, so it stands in as the reference price
+    let reference_out = probe_out.map(|out| crate::swap::reference_output(out, amount));
+    
+    if cfg.is_paper() {
+        let (tokens, sol_spent) = match crate::paper::buy(&wallet.name, mint, amount, reference_out, &cfg, &db).await {
+            Ok(fill) => fill,
+            Err(e) if e.downcast_ref::<crate::guards::GuardViolation>().is_some() => {
+                crate::notifier::log(format!("{} 🛡️ Skipping {}: rejected by {}", crate::paper::TAG, mint, e)).await;
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        let fill = crate::fills::Fill { sol_spent, fee: 0, rent: 0, tokens };
+        crate::notifier::log(format!("{} BOUGHT {} with {} | {} tokens for {:.4} SOL{}", crate::paper::TAG, mint, wallet.name, tokens, fill.sol_spent_f64(), risk_note)).await;
+        let position = crate::strategy::Position::new(mint, source, fill.sol_spent_f64(), Some(fill));
+        spawn_manage(permit.into_position(position.cost_sol), position, cfg, wallet, db);
+        return Ok(());
+    }
+    
+    // Climb the retry ladder: each retry bumps slippage and fee, but never chases a pump
+    let deadline = allocation.signal_at + Duration::from_secs(cfg.retry.deadline_secs);
+    let token_program = mint_info.as_ref().map_or(crate::token::TOKEN_PROGRAM, |info| info.program);
+    let token_account = crate::token::associated_token_address(&payer.pubkey(), &mint, &token_program);
+    let tokens_before = crate::token::token_balance(&rpc, &token_account).await?;
+    let mut attempt: u32 = 0;
+    let signature = loop {
+        let attempt_cfg = cfg.retry.attempt_config(&cfg, attempt);
+        if attempt > 0 {
+            // Without both prices there's no telling whether a retry would chase a pump
+            let msg = match probe_out {
+                None => Some(format!("🏃 Not retrying {}: no price from the signal to compare against", mint)),
+                Some(probe_at_signal) => match crate::swap::check_token_liquidity(&cfg, &mint, size_sol).await {
+                    Ok(Some(probe_now)) => {
+                        let chased = crate::retry::chase_pct(probe_at_signal, probe_now);
+                        (chased > cfg.retry.max_chase_pct).then(|| {
+                            format!("🏃 Not retrying {}: price is up {:.1}% since the signal (max {:.1}%)", mint, chased, cfg.retry.max_chase_pct)
+                        })
+                    }
+                    Ok(None) => Some(format!("🏃 Not retrying {}: no route to re-check the price", mint)),
+                    Err(e) => Some(format!("🏃 Not retrying {}: could not re-check the price: {}", mint, e)),
+                },
+            };
+            if let Some(msg) = msg {
+                tracing::warn!("{}", msg);
+                crate::notifier::log(msg).await;
+                return Ok(());
+            }
+        }
+        
+        tracing::info!("Getting swap transaction from Jupiter (attempt {}, {} bps slippage, {} µlamports/CU)...",
+            attempt + 1, attempt_cfg.slippage_bps, attempt_cfg.priority_fee_microlamports);
+        let extras = crate::swap::ExtraInstructions { reference_out, ..Default::default() };
+        let result = match crate::swap::build_swap_transaction(&attempt_cfg, &rpc, &payer, sol_mint, mint, amount, &extras).await {
+            Ok(tx) => {
+                tracing::info!("Sending transaction to buy {}...", mint);
+                send_buy(&rpc, &tx, &token_account, tokens_before).await
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(signature) => break signature,
+            Err(e) if e.downcast_ref::<crate::guards::GuardViolation>().is_some() => {
+                let msg = format!("🛡️ Skipping {}: rejected by {}", mint, e);
+                tracing::warn!("{}", msg);
+                crate::notifier::log(msg).await;
+                return Ok(());
+            }
+            Err(e) => {
+                attempt += 1;
+                let retry = attempt < cfg.retry.max_attempts
+                    && crate::retry::is_retryable(&e)
+                    && Instant::now() + cfg.retry.backoff() < deadline;
+                if !retry {
+                    let msg = format!("❌ Buy of {} failed after {} attempt(s): {}", mint, attempt, e);
+                    tracing::error!("{}", msg);
+                    crate::notifier::log(msg).await;
+                    return Err(e);
                 }
+                tracing::warn!("Buy attempt {}/{} for {} failed: {}. Retrying...", attempt, cfg.retry.max_attempts, mint, e);
+                tokio::time::sleep(cfg.retry.backoff()).await;
             }
-            
-            tokio::spawn(crate::strategy::manage(mint, cfg, payer.clone()));
-            Ok(())
+        }
+    };
+    
+    tracing::info!("✅ Successfully bought {}, signature: {}", mint, signature);
+    
+    // Read what actually filled so entry cost and PnL aren't guesses
+    let fill = match crate::fills::fetch_fill(&rpc, &signature, &payer.pubkey(), &mint).await {
+        Ok(fill) => {
+            tracing::info!("🧾 Fill for {}: {} tokens for {:.6} SOL (fee {} lamports, rent {} lamports)",
+                mint, fill.tokens, fill.sol_spent_f64(), fill.fee, fill.rent);
+            Some(fill)
         }
         Err(e) => {
-            let msg = format!("❌ Transaction failed for {}: {}", mint, e);
-            tracing::error!("{}", msg);
-            crate::notifier::log(msg).await;
-            Err(e.into())
+            tracing::warn!("Could not parse fill for {}, using the requested size: {}", mint, e);
+            None
         }
+    };
+    let fill_note = fill.map_or(String::new(), |f| format!(" | {} tokens for {:.4} SOL", f.tokens, f.sol_spent_f64()));
+    
+    // Check balance after successful trade
+    match get_sol_balance(&cfg.rpc_http, &payer.pubkey()).await {
+        Ok(new_balance) => {
+            tracing::info!("💰 Balance after trade: {:.4} SOL", new_balance);
+            crate::notifier::log(format!("🟢 BOUGHT {} with {}{} | TX: {} | Balance: {:.4} SOL{}", mint, wallet.name, fill_note, signature, new_balance, risk_note)).await;
+        }
+        Err(e) => {
+            tracing::warn!("Could not check balance after trade: {}", e);
+            crate::notifier::log(format!("🟢 BOUGHT {} with {}{} - TX: {}{}", mint, wallet.name, fill_note, signature, risk_note)).await;
+        }
+    }
+    
+    let cost_sol = fill.map_or(size_sol, |f| f.sol_spent_f64());
+    let position = crate::strategy::Position::new(mint, source, cost_sol, fill);
+    spawn_manage(permit.into_position(cost_sol), position, cfg, wallet, db);
+    Ok(())
+}
+
+/// Send a buy and wait for it to confirm. A send the node refuses (a failed preflight, say) never
+/// went out, so it fails straight away; only a buy that was sent is watched for on chain.
+async fn send_buy(rpc: &RpcClient, tx: &VersionedTransaction, token_account: &Pubkey, tokens_before: u64) -> Result<Signature> {
+    let signature = tx.signatures[0];
+    if let Err(e) = rpc.send_transaction(tx).await {
+        if matches!(e.kind(), ClientErrorKind::RpcError(RpcError::RpcResponseError { .. })) {
+            return Err(e.into());
+        }
+        // Lost on the way back rather than refused: the node may have taken it
+        tracing::warn!("Sending buy {} failed, watching for it anyway: {}", signature, e);
+    }
+    match buy_landed(rpc, tx, token_account, tokens_before).await {
+        Ok(true) => Ok(signature),
+        Ok(false) => Err(anyhow::anyhow!("Buy {} failed or expired before confirming", signature)),
+        Err(e) => Err(crate::retry::MaybeLanded(format!("could not confirm {}: {}", signature, e)).into()),
+    }
+}
+
+/// Whether a sent buy went through, waiting while its blockhash could still land it,
+/// so an answer of false is final
+async fn buy_landed(rpc: &RpcClient, tx: &VersionedTransaction, token_account: &Pubkey, tokens_before: u64) -> Result<bool> {
+    let signature = tx.signatures[0];
+    loop {
+        if let Some(status) = rpc.get_signature_statuses_with_history(&[signature]).await?.value[0].as_ref() {
+            if status.err.is_some() || status.satisfies_commitment(rpc.commitment()) {
+                return Ok(status.err.is_none());
+            }
+        }
+        if crate::token::token_balance(rpc, token_account).await? > tokens_before {
+            return Ok(true);
+        }
+        if !rpc.is_blockhash_valid(tx.message.recent_blockhash(), CommitmentConfig::processed()).await? {
+            // One last look in case it landed in the final valid slot
+            return Ok(rpc.get_signature_statuses_with_history(&[signature]).await?.value[0].as_ref().is_some_and(|s| s.err.is_none()));
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+/// Manage the position in the background, counting it as open until the manager returns
+fn spawn_manage(slot: crate::coordinator::OpenPosition, position: crate::strategy::Position, cfg: Config, wallet: Wallet, db: ProfitDatabase) {
+    tokio::spawn(async move {
+        let _slot = slot;
+        crate::strategy::manage(position, cfg, wallet, db).await;
+    });
+}
+
+/// Put positions left open by a previous run back under management; returns how many resumed
+pub async fn resume_positions(cfg: &Config, services: &Services, db: &ProfitDatabase) -> Result<usize> {
+    let rpc = RpcClient::new(cfg.rpc_http.clone());
+    let mut resumed = 0;
+    for record in db.open_positions(cfg.is_paper())? {
+        let Some(wallet) = services.wallets.get(&record.wallet).cloned() else {
+            tracing::warn!("Open position {} belongs to unknown wallet {}, leaving it", record.mint, record.wallet);
+            continue;
+        };
+        let position = crate::strategy::Position::from_record(&record)?;
+        // Tokens sold while the bot was down leave nothing to manage
+        let held = if cfg.is_paper() {
+            db.paper_token_balance(&wallet.name, &record.mint).map_err(anyhow::Error::from)
+        } else {
+            held_tokens(&rpc, &wallet, &position.mint).await
+        };
+        match held {
+            Ok(0) => {
+                tracing::info!("Position {} ({}) was closed while the bot was down", position.mint, wallet.name);
+                db.set_position_status(record.id, crate::profit_db::POSITION_CLOSED)?;
+                continue;
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Could not check the balance of {} ({}), resuming anyway: {}", position.mint, wallet.name, e),
+        }
+        tracing::info!("♻️ Resuming {} ({}): {:.4} SOL at risk, stop {:?}", position.mint, wallet.name, position.remaining_cost, position.stop_price);
+        let slot = services.coordinator.restore(&wallet.name, position.mint, position.remaining_cost);
+        spawn_manage(slot, position, cfg.clone(), wallet, db.clone());
+        resumed += 1;
+    }
+    Ok(resumed)
+}
+
+async fn held_tokens(rpc: &RpcClient, wallet: &Wallet, mint: &Pubkey) -> Result<u64> {
+    let mint_info = crate::token::fetch_mint(rpc, mint).await?;
+    let account = crate::token::associated_token_address(&wallet.pubkey(), mint, &mint_info.program);
+    crate::token::token_balance(rpc, &account).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::message::{v0, VersionedMessage};
+    use solana_sdk::signature::Keypair;
+    use wiremock::matchers::{body_partial_json, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_preflight_failure_fails_without_waiting() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "method": "sendTransaction" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "error": {
+                    "code": -32002,
+                    "message": "Transaction simulation failed: Error processing Instruction 2: custom program error: 0x1771",
+                    "data": { "err": { "InstructionError": [2, { "Custom": 6001 }] }, "logs": [], "accounts": null, "unitsConsumed": 0 },
+                },
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let payer = Keypair::new();
+        let message = v0::Message::try_compile(&payer.pubkey(), &[], &[], Hash::new_unique()).unwrap();
+        let tx = VersionedTransaction::try_new(VersionedMessage::V0(message), &[&payer]).unwrap();
+        let rpc = RpcClient::new(server.uri());
+        let started = Instant::now();
+        let err = send_buy(&rpc, &tx, &Pubkey::new_unique(), 0).await.unwrap_err();
+
+        // Refused before it went out: retryable at once, and nothing was polled for it
+        assert!(crate::retry::is_retryable(&err));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
     }
-}
\ No newline at end of file
+}
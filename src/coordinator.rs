@@ -0,0 +1,299 @@
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// Limits on how much the bot buys at once (`[coordinator]` table in config.toml)
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct CoordinatorConfig {
+    /// Buys that can be between signal and confirmation at the same time
+    pub max_concurrent_buys: usize,
+    pub max_open_positions: usize,
+    /// Cost of open positions plus in-flight buys, in SOL
+    pub max_sol_at_risk: Option<f64>,
+    /// Wait for a slot instead of rejecting when a limit is hit
+    pub queue: bool,
+    pub queue_timeout_secs: u64,
+}
+
+impl Default for CoordinatorConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_buys: 3,
+            max_open_positions: 10,
+            max_sol_at_risk: None,
+            queue: true,
+            queue_timeout_secs: 15,
+        }
+    }
+}
+
+/// Why a buy was not let through
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rejection {
+    MintBusy,
+    ConcurrentBuys(usize),
+    OpenPositions(usize),
+    SolAtRisk { at_risk: f64, size: f64, max: f64 },
+}
+
+impl Rejection {
+    /// Limits that free up as other trades finish; a busy mint is a duplicate signal
+    fn queueable(&self) -> bool {
+        !matches!(self, Rejection::MintBusy)
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::MintBusy => write!(f, "a buy of this mint is already in progress"),
+            Rejection::ConcurrentBuys(max) => write!(f, "{} buys already in flight", max),
+            Rejection::OpenPositions(max) => write!(f, "{} positions already open", max),
+            Rejection::SolAtRisk { at_risk, size, max } => {
+                write!(f, "{:.3} SOL at risk + {:.3} SOL would exceed {:.3} SOL", at_risk, size, max)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+#[derive(Default)]
+struct Book {
+    locked_mints: HashSet<Pubkey>,
+    in_flight: usize,
+    reserved_sol: f64,
+    /// (wallet, mint, cost) of each open position, keyed per position so two in the same mint don't collide
+    open: HashMap<u64, (String, Pubkey, f64)>,
+    next_position: u64,
+}
+
+impl Book {
+    fn at_risk(&self) -> f64 {
+        self.reserved_sol + self.open.values().map(|(_, _, cost)| cost).sum::<f64>()
+    }
+}
+
+/// Serializes buys per mint and keeps the bot within its concurrency and exposure limits
+pub struct TradeCoordinator {
+    config: CoordinatorConfig,
+    book: Mutex<Book>,
+    released: Notify,
+}
+
+impl TradeCoordinator {
+    pub fn new(config: CoordinatorConfig) -> Arc<Self> {
+        Arc::new(Self { config, book: Mutex::new(Book::default()), released: Notify::new() })
+    }
+
+    /// Hold `mint` for the whole signal so a duplicate can't buy it twice
+    pub fn lock_mint(self: &Arc<Self>, mint: Pubkey) -> Result<MintLock, Rejection> {
+        if !self.book.lock().unwrap().locked_mints.insert(mint) {
+            return Err(Rejection::MintBusy);
+        }
+        Ok(MintLock { coordinator: self.clone(), mint })
+    }
+
+    fn try_admit(self: &Arc<Self>, wallet: &str, mint: Pubkey, size_sol: f64) -> Result<BuyPermit, Rejection> {
+        let mut book = self.book.lock().unwrap();
+        if book.in_flight >= self.config.max_concurrent_buys {
+            return Err(Rejection::ConcurrentBuys(self.config.max_concurrent_buys));
+        }
+        if book.open.len() + book.in_flight >= self.config.max_open_positions {
+            return Err(Rejection::OpenPositions(self.config.max_open_positions));
+        }
+        if let Some(max) = self.config.max_sol_at_risk {
+            let at_risk = book.at_risk();
+            if at_risk + size_sol > max {
+                return Err(Rejection::SolAtRisk { at_risk, size: size_sol, max });
+            }
+        }
+        book.in_flight += 1;
+        book.reserved_sol += size_sol;
+        Ok(BuyPermit { coordinator: self.clone(), wallet: wallet.to_string(), mint, size_sol })
+    }
+
+    /// Reserve a buy slot, waiting up to `queue_timeout_secs` for one when queueing is on
+    pub async fn admit(self: &Arc<Self>, wallet: &str, mint: Pubkey, size_sol: f64) -> Result<BuyPermit, Rejection> {
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(self.config.queue_timeout_secs);
+        loop {
+            // Register before checking so a release in between isn't missed
+            let released = self.released.notified();
+            match self.try_admit(wallet, mint, size_sol) {
+                Ok(permit) => return Ok(permit),
+                Err(reason) if !self.config.queue || !reason.queueable() => return Err(reason),
+                Err(reason) => {
+                    tracing::info!("⏳ Buy of {} queued: {}", mint, reason);
+                    if tokio::time::timeout_at(deadline, released).await.is_err() {
+                        return Err(reason);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Count an open position against the limits, such as one carried over from a previous run
+    pub fn restore(self: &Arc<Self>, wallet: &str, mint: Pubkey, cost_sol: f64) -> OpenPosition {
+        let mut book = self.book.lock().unwrap();
+        let key = book.next_position;
+        book.next_position += 1;
+        book.open.insert(key, (wallet.to_string(), mint, cost_sol));
+        OpenPosition { coordinator: self.clone(), key }
+    }
+
+    /// Whether any wallet has an open position in `mint`
+    pub fn holds(&self, mint: &Pubkey) -> bool {
+        self.book.lock().unwrap().open.values().any(|(_, open, _)| open == mint)
+    }
+
+    /// (in-flight buys, open positions, SOL at risk)
+    pub fn snapshot(&self) -> (usize, usize, f64) {
+        let book = self.book.lock().unwrap();
+        (book.in_flight, book.open.len(), book.at_risk())
+    }
+
+    fn release(&self, update: impl FnOnce(&mut Book)) {
+        update(&mut self.book.lock().unwrap());
+        self.released.notify_waiters();
+    }
+}
+
+/// Exclusive hold on a mint while a signal for it is being handled
+pub struct MintLock {
+    coordinator: Arc<TradeCoordinator>,
+    mint: Pubkey,
+}
+
+impl Drop for MintLock {
+    fn drop(&mut self) {
+        let mint = self.mint;
+        self.coordinator.release(|book| {
+            book.locked_mints.remove(&mint);
+        });
+    }
+}
+
+/// A reserved in-flight buy; dropping it without a fill frees the slot
+pub struct BuyPermit {
+    coordinator: Arc<TradeCoordinator>,
+    wallet: String,
+    mint: Pubkey,
+    size_sol: f64,
+}
+
+impl BuyPermit {
+    /// The buy filled: keep counting its cost until the position is closed
+    pub fn into_position(self, cost_sol: f64) -> OpenPosition {
+        self.coordinator.restore(&self.wallet, self.mint, cost_sol)
+    }
+}
+
+impl Drop for BuyPermit {
+    fn drop(&mut self) {
+        let size = self.size_sol;
+        self.coordinator.release(|book| {
+            book.in_flight -= 1;
+            book.reserved_sol = (book.reserved_sol - size).max(0.0);
+        });
+    }
+}
+
+/// An open position counted against the limits until dropped
+pub struct OpenPosition {
+    coordinator: Arc<TradeCoordinator>,
+    key: u64,
+}
+
+impl Drop for OpenPosition {
+    fn drop(&mut self) {
+        let key = self.key;
+        self.coordinator.release(|book| {
+            book.open.remove(&key);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coordinator(max_concurrent_buys: usize, max_open_positions: usize, max_sol_at_risk: Option<f64>) -> Arc<TradeCoordinator> {
+        TradeCoordinator::new(CoordinatorConfig {
+            max_concurrent_buys,
+            max_open_positions,
+            max_sol_at_risk,
+            queue: false,
+            queue_timeout_secs: 0,
+        })
+    }
+
+    #[test]
+    fn test_mint_lock() {
+        let coordinator = coordinator(3, 10, None);
+        let mint = Pubkey::new_unique();
+        let lock = coordinator.lock_mint(mint).unwrap();
+        assert_eq!(coordinator.lock_mint(mint).err(), Some(Rejection::MintBusy));
+        assert!(coordinator.lock_mint(Pubkey::new_unique()).is_ok());
+        drop(lock);
+        assert!(coordinator.lock_mint(mint).is_ok());
+    }
+
+    #[test]
+    fn test_positions_in_the_same_mint_count_separately() {
+        let coordinator = coordinator(3, 10, None);
+        let mint = Pubkey::new_unique();
+        let first = coordinator.restore("a", mint, 0.2);
+        let second = coordinator.restore("a", mint, 0.3);
+        assert_eq!(coordinator.snapshot().1, 2);
+        drop(first);
+        let (_, open, at_risk) = coordinator.snapshot();
+        assert_eq!(open, 1);
+        assert!((at_risk - 0.3).abs() < 1e-9);
+        assert!(coordinator.holds(&mint));
+        drop(second);
+        assert!(!coordinator.holds(&mint));
+    }
+
+    #[tokio::test]
+    async fn test_limits() {
+        let coordinator = coordinator(2, 3, Some(1.0));
+        let first = coordinator.admit("a", Pubkey::new_unique(), 0.4).await.unwrap();
+        let second = coordinator.admit("a", Pubkey::new_unique(), 0.4).await.unwrap();
+        assert_eq!(coordinator.admit("a", Pubkey::new_unique(), 0.1).await.err(), Some(Rejection::ConcurrentBuys(2)));
+
+        let _open = first.into_position(0.5);
+        assert!(matches!(coordinator.admit("a", Pubkey::new_unique(), 0.2).await, Err(Rejection::SolAtRisk { .. })));
+        let third = coordinator.admit("a", Pubkey::new_unique(), 0.05).await.unwrap();
+        assert_eq!(coordinator.snapshot().0, 2);
+
+        let _open2 = third.into_position(0.1);
+        drop(second);
+        let (in_flight, open, at_risk) = coordinator.snapshot();
+        assert_eq!((in_flight, open), (0, 2));
+        assert!((at_risk - 0.6).abs() < 1e-9);
+        let _fourth = coordinator.admit("a", Pubkey::new_unique(), 0.1).await.unwrap();
+        assert_eq!(coordinator.admit("a", Pubkey::new_unique(), 0.1).await.err(), Some(Rejection::OpenPositions(3)));
+    }
+
+    #[tokio::test]
+    async fn test_queued_buy_waits_for_slot() {
+        let coordinator = TradeCoordinator::new(CoordinatorConfig {
+            max_concurrent_buys: 1,
+            queue_timeout_secs: 5,
+            ..Default::default()
+        });
+        let permit = coordinator.admit("a", Pubkey::new_unique(), 0.1).await.unwrap();
+        let waiting = {
+            let coordinator = coordinator.clone();
+            tokio::spawn(async move { coordinator.admit("b", Pubkey::new_unique(), 0.1).await.is_ok() })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        drop(permit);
+        assert!(waiting.await.unwrap());
+    }
+}
@@ -0,0 +1,211 @@
+use anyhow::{anyhow, Result};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::fmt;
+use std::str::FromStr;
+
+pub const PUMP_FUN_PROGRAM: &str = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P";
+pub const LAUNCHLAB_PROGRAM: &str = "LanMV9sAd7wArD4vJFi2qDdfnVhFxYSUg6eADduJ3uj";
+const WSOL_MINT: &str = "So11111111111111111111111111111111111111112";
+
+/// Anchor account discriminators: the first 8 bytes of sha256("account:<Name>")
+const PUMP_CURVE_DISCRIMINATOR: [u8; 8] = [23, 183, 248, 55, 96, 216, 172, 96];
+const LAUNCHLAB_POOL_DISCRIMINATOR: [u8; 8] = [247, 237, 227, 245, 215, 195, 222, 70];
+
+/// pump.fun tokens are always minted with 6 decimals
+const PUMP_FUN_DECIMALS: u8 = 6;
+const SOL_DECIMALS: u8 = 9;
+
+/// Launchpad whose bonding curve a token trades on until it migrates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Launchpad {
+    PumpFun,
+    /// Raydium LaunchLab, used by LetsBonk and others
+    LaunchLab,
+}
+
+impl Launchpad {
+    pub fn program(&self) -> Pubkey {
+        match self {
+            Launchpad::PumpFun => Pubkey::from_str(PUMP_FUN_PROGRAM).unwrap(),
+            Launchpad::LaunchLab => Pubkey::from_str(LAUNCHLAB_PROGRAM).unwrap(),
+        }
+    }
+
+    /// Curve account of `mint` on this launchpad
+    pub fn curve_address(&self, mint: &Pubkey) -> Pubkey {
+        let (address, _) = match self {
+            Launchpad::PumpFun => Pubkey::find_program_address(&[b"bonding-curve", mint.as_ref()], &self.program()),
+            Launchpad::LaunchLab => {
+                let wsol = Pubkey::from_str(WSOL_MINT).unwrap();
+                Pubkey::find_program_address(&[b"pool", mint.as_ref(), wsol.as_ref()], &self.program())
+            }
+        };
+        address
+    }
+}
+
+impl fmt::Display for Launchpad {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Launchpad::PumpFun => write!(f, "pump.fun"),
+            Launchpad::LaunchLab => write!(f, "LaunchLab"),
+        }
+    }
+}
+
+/// The parts of a curve account the exit logic needs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CurveState {
+    /// Effective reserves the curve prices against, in raw units
+    pub sol_reserves: u64,
+    pub token_reserves: u64,
+    pub token_decimals: u8,
+    /// The curve is done and the token has moved (or is moving) to an AMM
+    pub complete: bool,
+    pub creator: Option<Pubkey>,
+}
+
+impl CurveState {
+    /// SOL per whole token
+    pub fn price(&self) -> f64 {
+        if self.token_reserves == 0 {
+            return 0.0;
+        }
+        let sol = self.sol_reserves as f64 / 10f64.powi(SOL_DECIMALS as i32);
+        let tokens = self.token_reserves as f64 / 10f64.powi(self.token_decimals as i32);
+        sol / tokens
+    }
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64> {
+    let bytes = data.get(offset..offset + 8).ok_or_else(|| anyhow!("Curve account too short"))?;
+    Ok(u64::from_le_bytes(bytes.try_into()?))
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Option<Pubkey> {
+    let key: [u8; 32] = data.get(offset..offset + 32)?.try_into().ok()?;
+    let key = Pubkey::new_from_array(key);
+    (key != Pubkey::default()).then_some(key)
+}
+
+fn check_discriminator(data: &[u8], expected: &[u8; 8], launchpad: Launchpad) -> Result<()> {
+    if data.get(..8) != Some(expected.as_slice()) {
+        return Err(anyhow!("Not a {} curve account", launchpad));
+    }
+    Ok(())
+}
+
+/// pump.fun `BondingCurve`: virtual token/SOL reserves, real token/SOL reserves, supply, complete, creator
+fn decode_pump_fun(data: &[u8]) -> Result<CurveState> {
+    check_discriminator(data, &PUMP_CURVE_DISCRIMINATOR, Launchpad::PumpFun)?;
+    let complete = *data.get(48).ok_or_else(|| anyhow!("Curve account too short"))? != 0;
+    Ok(CurveState {
+        sol_reserves: read_u64(data, 16)?,
+        token_reserves: read_u64(data, 8)?,
+        token_decimals: PUMP_FUN_DECIMALS,
+        complete,
+        creator: read_pubkey(data, 49),
+    })
+}
+
+/// LaunchLab `PoolState`; the price is (virtual_quote + real_quote) / (virtual_base - real_base)
+fn decode_launchlab(data: &[u8]) -> Result<CurveState> {
+    check_discriminator(data, &LAUNCHLAB_POOL_DISCRIMINATOR, Launchpad::LaunchLab)?;
+    // epoch u64, auth_bump u8, then status, base_decimals, quote_decimals, migrate_type
+    let header = data.get(16..21).ok_or_else(|| anyhow!("Curve account too short"))?;
+    let (status, base_decimals, quote_decimals) = (header[1], header[2], header[3]);
+    if quote_decimals != SOL_DECIMALS {
+        return Err(anyhow!("LaunchLab pool is not quoted in SOL"));
+    }
+    // supply and total_base_sell come first
+    let virtual_base = read_u64(data, 37)?;
+    let virtual_quote = read_u64(data, 45)?;
+    let real_base = read_u64(data, 53)?;
+    let real_quote = read_u64(data, 61)?;
+    Ok(CurveState {
+        sol_reserves: virtual_quote.saturating_add(real_quote),
+        token_reserves: virtual_base.saturating_sub(real_base),
+        token_decimals: base_decimals,
+        complete: status != 0,
+        // after the fees, vesting schedule and six config/mint/vault keys
+        creator: read_pubkey(data, 333),
+    })
+}
+
+pub fn decode(launchpad: Launchpad, data: &[u8]) -> Result<CurveState> {
+    match launchpad {
+        Launchpad::PumpFun => decode_pump_fun(data),
+        Launchpad::LaunchLab => decode_launchlab(data),
+    }
+}
+
+/// Which launchpad `mint` was launched on, judged by which program owns its curve account
+pub async fn find_curve(rpc: &RpcClient, mint: &Pubkey) -> Result<(Launchpad, Pubkey)> {
+    lookup_curve(rpc, mint).await?.ok_or_else(|| anyhow!("No pump.fun or LaunchLab curve found for {}", mint))
+}
+
+/// Like `find_curve`, but tells a token without a curve (`Ok(None)`) apart from a failed lookup
+pub async fn lookup_curve(rpc: &RpcClient, mint: &Pubkey) -> Result<Option<(Launchpad, Pubkey)>> {
+    let candidates = [Launchpad::PumpFun, Launchpad::LaunchLab];
+    let addresses: Vec<Pubkey> = candidates.iter().map(|launchpad| launchpad.curve_address(mint)).collect();
+    let accounts = rpc.get_multiple_accounts(&addresses).await?;
+    Ok(candidates.into_iter().zip(addresses).zip(accounts).find_map(|((launchpad, address), account)| {
+        account.filter(|account| account.owner == launchpad.program()).map(|_| (launchpad, address))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose, Engine};
+
+    /// A pump.fun curve part way up: 800M tokens and 40.2375 SOL virtual reserves
+    const PUMP_CURVE: &str = "F7f4N2DYrGAAANKDmNcCAGCGV14JAAAAAGi/NwfZAQBg2jNiAgAAAACAxqR+jQMAAAHs2gTVJf9RP2Qf/RvS58dJbUntgV0yI0qrsJatwnEBAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA";
+    /// A LaunchLab pool with 100M of its tokens sold for 3.2 SOL
+    const LAUNCHLAB_POOL: &str = "9+3j9dfD3kYsAwAAAAAAAP4ABgkBAIDGpH6NAwAAeMX7UdECAN50Dj7pzwMA168w/AYAAAAAQHoQ81oAAAAgvL4AAAAAABJlyhMAAAAAEnoAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAgAHCdDllDFxaa07ZQWO13etEdjYscTgOYT+iDf/871DSlPzODMiFh4Qwmdhd2AWu7xsJpjsNsd0+TcYqNDwdtWDKUqOcyYjHT+UfVWepuZmOuGbLWgqb8H+QXWhRdLw2BpuIV/6rgYT7aH9jRhjANdrEOdwa6ztVmKDwAAAAAAExXeRXc7JeXBEuq62zqpH4q4XuIdKtpqAB55pbZrH6BbbSJCIAKFuAsISxw6CrIJ0rtZZ9ol+nL0RrmqoUgQBOW9YEQnlkmHjpIUBnYTEEZ9pr4Kzahi/I5t14WNAKsv4AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA";
+
+    fn bytes(fixture: &str) -> Vec<u8> {
+        general_purpose::STANDARD.decode(fixture).unwrap()
+    }
+
+    #[test]
+    fn test_decode_pump_fun() {
+        let data = bytes(PUMP_CURVE);
+        assert_eq!(data.len(), 150);
+        let curve = decode(Launchpad::PumpFun, &data).unwrap();
+        assert_eq!((curve.token_reserves, curve.sol_reserves), (800_000_000_000_000, 40_237_500_000));
+        assert!(!curve.complete);
+        assert!(curve.creator.is_some());
+        assert!((curve.price() - 5.0296875e-8).abs() < 1e-15);
+        // The wrong decoder for the account is refused rather than misread
+        assert!(decode(Launchpad::LaunchLab, &data).is_err());
+        assert!(decode(Launchpad::PumpFun, &data[..40]).is_err());
+    }
+
+    #[test]
+    fn test_decode_launchlab() {
+        let data = bytes(LAUNCHLAB_POOL);
+        assert_eq!(data.len(), 429);
+        let curve = decode(Launchpad::LaunchLab, &data).unwrap();
+        assert_eq!(curve.token_decimals, 6);
+        assert_eq!(curve.sol_reserves, 30_000_852_951 + 3_200_000_000);
+        assert_eq!(curve.token_reserves, 1_073_025_605_596_382 - 100_000_000_000_000);
+        assert!(!curve.complete);
+        assert!((curve.price() - 3.4121253e-8).abs() < 1e-14);
+        assert!(decode(Launchpad::PumpFun, &data).is_err());
+
+        let mut migrated = data.clone();
+        migrated[17] = 2;
+        assert!(decode(Launchpad::LaunchLab, &migrated).unwrap().complete);
+    }
+
+    #[test]
+    fn test_price_is_normalized_by_decimals() {
+        // The same reserves in whole units give the same price whatever the decimals
+        let six = CurveState { sol_reserves: 30_000_000_000, token_reserves: 1_000_000_000_000, token_decimals: 6, complete: false, creator: None };
+        let nine = CurveState { token_reserves: 1_000_000_000_000_000, token_decimals: 9, ..six };
+        assert!((six.price() - 3e-5).abs() < 1e-15);
+        assert!((six.price() - nine.price()).abs() < 1e-15);
+    }
+}
@@ -2,7 +2,7 @@ use anyhow::{anyhow, Context, Result};
 use regex::Regex;
 use solana_sdk::pubkey::Pubkey;
 use std::str::FromStr;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use futures_util::{SinkExt, StreamExt};
@@ -11,12 +11,14 @@ use tokio::sync::mpsc;
 use tokio::time::{interval, Interval};
 use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
 use std::time::Duration;
-use solana_sdk::signer::keypair::Keypair;
-use crate::{Config, get_sol_balance};
+use crate::Config;
+use crate::sell::SellAmount;
+use crate::profit_db::ProfitDatabase;
+use crate::Services;
 
-pub async fn run(config: Config, payer: Arc<Keypair>, connected: Arc<AtomicBool>) -> Result<()> {
+pub async fn run(config: Config, services: Services, db: ProfitDatabase, connected: Arc<AtomicBool>) -> Result<()> {
     loop {
-        match connect_and_listen(&config, payer.clone(), &connected).await {
+        match connect_and_listen(&config, services.clone(), &db, &connected).await {
             Ok(_) => break,
             Err(e) => {
                 error!("Discord connection error: {}. Reconnecting in 5s...", e);
@@ -27,7 +29,7 @@ pub async fn run(config: Config, payer: Arc<Keypair>, connected: Arc<AtomicBool>
     Ok(())
 }
 
-async fn connect_and_listen(config: &Config, payer: Arc<Keypair>, connected: &Arc<AtomicBool>) -> Result<()> {
+async fn connect_and_listen(config: &Config, services: Services, db: &ProfitDatabase, connected: &Arc<AtomicBool>) -> Result<()> {
     let (ws_stream, _) = connect_async("wss://gateway.discord.gg/?v=10&encoding=json")
         .await
         .context("Failed to connect to Discord Gateway")?;
@@ -141,17 +143,33 @@ async fn connect_and_listen(config: &Config, payer: Arc<Keypair>, connected: &Ar
                             info!("📝 Message content: '{}'", content);
                             
                             let config_clone = config.clone();
-                            let payer_clone = payer.clone();
                             tokio::spawn(crate::buy::execute(
                                 token_address,
+                                format!("discord:{}:{}", channel_id, author_name),
                                 config_clone,
-                                payer_clone,
+                                services.clone(),
+                                db.clone(),
                             ));
                             let signal_notification = format!(
                                 "🚀 SIGNAL DETECTED!\nToken: {}\nFrom: {}\nChannel: {}\nMessage: {}",
                                 token_address, author_name, channel_id, content
                             );
                             crate::notifier::log(signal_notification).await;
+                        } else if let Some((token_address, amount)) = parse_exit_signal(content) {
+                            // Only positions the bot opened; a stray command can't dump anything else
+                            if !services.coordinator.holds(&token_address) {
+                                warn!("Ignoring EXIT for {}: no open position", token_address);
+                                continue;
+                            }
+                            info!("🔴 EXIT SIGNAL! Token: {} | Amount: {} | From: {}", token_address, amount, author_name);
+                            let (config_clone, wallets_clone, db_clone) = (config.clone(), services.wallets.clone(), db.clone());
+                            tokio::spawn(async move {
+                                crate::sell::execute_all_wallets(token_address, amount, config_clone, &wallets_clone, db_clone).await
+                            });
+                            crate::notifier::log(format!(
+                                "🔴 EXIT SIGNAL!\nToken: {}\nSell: {}\nFrom: {}\nChannel: {}",
+                                token_address, amount, author_name, channel_id
+                            )).await;
                         }
                     }
                 }
@@ -188,6 +206,19 @@ async fn connect_and_listen(config: &Config, payer: Arc<Keypair>, connected: &Ar
     Err(anyhow!("WebSocket disconnected"))
 }
 
+/// Detect the exit command `EXIT: <mint> [pct%]` on a line of its own; no percent sells everything
+fn parse_exit_signal(content: &str) -> Option<(Pubkey, SellAmount)> {
+    let exit_re = Regex::new(r"(?im)^\s*exit\s*:\s*([1-9A-HJ-NP-Za-km-z]{32,44})(?:\s+(\d{1,3}\s*%))?\s*$").unwrap();
+    let cap = exit_re.captures(content)?;
+    let mint = Pubkey::from_str(&cap[1]).ok()?;
+    let amount = match cap.get(2) {
+        // An out-of-range percent is a typo, not a reason to sell everything
+        Some(pct) => pct.as_str().parse::<SellAmount>().ok()?,
+        None => SellAmount::All,
+    };
+    Some((mint, amount))
+}
+
 async fn parse_trading_signal(content: &str) -> Option<Pubkey> {
     // First, check if message contains "CA" (Contract Address)
     let signal_patterns = [r"(?i)\b(CA)\b"];
@@ -302,3 +333,19 @@ async fn validate_token_on_chain(pubkey: &Pubkey) -> Result<bool, Box<dyn std::e
 }
 */
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_exit_signal() {
+        let mint = Pubkey::new_unique();
+        assert_eq!(parse_exit_signal(&format!("EXIT: {}", mint)), Some((mint, SellAmount::All)));
+        assert_eq!(parse_exit_signal(&format!("gm\nexit:{} 50%", mint)), Some((mint, SellAmount::Percent(50))));
+        // Buy calls that merely mention selling are not exits
+        assert_eq!(parse_exit_signal(&format!("CA: {} close to bonding, don't sell", mint)), None);
+        assert_eq!(parse_exit_signal(&format!("sell {}", mint)), None);
+        assert_eq!(parse_exit_signal(&format!("EXIT: {} 1000%", mint)), None);
+        assert_eq!(parse_exit_signal(&format!("EXIT: {} 0%", mint)), None);
+    }
+}
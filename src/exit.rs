@@ -0,0 +1,216 @@
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::signer::Signer;
+use crate::Config;
+use crate::profit_db::ProfitDatabase;
+use crate::sell::{SellAmount, SellOutcome, Venue};
+use crate::wallets::Wallet;
+
+/// How exits are retried until they fill (`[exit]` table in config.toml)
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ExitConfig {
+    /// Slippage added on each failed attempt, capped at `max_slippage_bps`
+    pub slippage_step_bps: u16,
+    pub max_slippage_bps: u16,
+    /// Priority fee multiplier per failed attempt, capped at `max_priority_fee_microlamports`
+    pub fee_multiplier: f64,
+    pub max_priority_fee_microlamports: u64,
+    /// Alert on every this many failures in a row
+    pub alert_after: u32,
+    /// First wait between attempts; doubles up to `max_backoff_secs`
+    pub backoff_ms: u64,
+    pub max_backoff_secs: u64,
+    /// Alternate with PumpPortal once Jupiter has failed `fallback_after` times
+    pub pumpportal_fallback: bool,
+    pub fallback_after: u32,
+    pub pumpportal_url: String,
+}
+
+impl Default for ExitConfig {
+    fn default() -> Self {
+        Self {
+            slippage_step_bps: 300,
+            max_slippage_bps: 5_000,
+            fee_multiplier: 2.0,
+            max_priority_fee_microlamports: 5_000_000,
+            alert_after: 3,
+            backoff_ms: 500,
+            max_backoff_secs: 30,
+            pumpportal_fallback: true,
+            fallback_after: 2,
+            pumpportal_url: "https://pumpportal.fun/api/trade-local".to_string(),
+        }
+    }
+}
+
+impl ExitConfig {
+    /// Venue for the next attempt after `failures` failed ones
+    pub fn venue(&self, failures: u32) -> Venue {
+        if self.pumpportal_fallback && failures >= self.fallback_after && (failures - self.fallback_after).is_multiple_of(2) {
+            Venue::PumpPortal
+        } else {
+            Venue::Jupiter
+        }
+    }
+
+    pub fn should_alert(&self, failures: u32) -> bool {
+        self.alert_after > 0 && failures.is_multiple_of(self.alert_after)
+    }
+
+    pub fn backoff(&self, failures: u32) -> Duration {
+        let wait = self.backoff_ms.saturating_mul(1 << failures.saturating_sub(1).min(10));
+        Duration::from_millis(wait).min(Duration::from_secs(self.max_backoff_secs))
+    }
+}
+
+/// Tokens of `mint` the wallet holds, from the paper ledger in paper mode
+async fn held(mint: &Pubkey, cfg: &Config, wallet: &Wallet, db: &ProfitDatabase) -> anyhow::Result<u64> {
+    if cfg.is_paper() {
+        return Ok(db.paper_token_balance(&wallet.name, &mint.to_string())?);
+    }
+    let rpc = RpcClient::new(cfg.rpc_http.clone());
+    let mint_info = crate::token::fetch_mint(&rpc, mint).await?;
+    let account = crate::token::associated_token_address(&wallet.keypair.pubkey(), mint, &mint_info.program);
+    crate::token::token_balance(&rpc, &account).await
+}
+
+/// (tokens to keep, tokens to sell) for a partial sell out of `balance`
+pub fn pin(sell_amount: SellAmount, balance: u64) -> (u64, u64) {
+    let amount = sell_amount.resolve(balance);
+    (balance - amount, amount)
+}
+
+/// Slack for the gap between our clock and block times when matching a sell to this exit
+const BLOCK_TIME_SLACK_SECS: i64 = 30;
+
+fn unix_now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64
+}
+
+/// The newest successful sell of `mint` by the wallet since `since`, read from its token account's history
+async fn find_sale(mint: &Pubkey, cfg: &Config, wallet: &Wallet, since: i64) -> anyhow::Result<Option<SellOutcome>> {
+    let rpc = RpcClient::new(cfg.rpc_http.clone());
+    let owner = wallet.keypair.pubkey();
+    let mint_info = crate::token::fetch_mint(&rpc, mint).await?;
+    let account = crate::token::associated_token_address(&owner, mint, &mint_info.program);
+    let recent = rpc.get_signatures_for_address(&account).await?;
+    for entry in recent.iter().take_while(|entry| entry.block_time.is_none_or(|time| time >= since)) {
+        if entry.err.is_some() {
+            continue;
+        }
+        let sale = crate::fills::fetch_sale(&rpc, &entry.signature.parse()?, &owner, mint).await?;
+        if sale.tokens > 0 {
+            tracing::info!("Found the unconfirmed sell of {} from {}: {}", mint, wallet.name, entry.signature);
+            return Ok(Some(SellOutcome { tokens_sold: sale.tokens, transfer_fee: 0, expected_sol: None, sol_received: sale.sol_received_f64() }));
+        }
+    }
+    Ok(None)
+}
+
+/// An earlier attempt sold the tokens but its confirmation was lost: book it from chain, or alert
+async fn landed_unconfirmed(mint: &Pubkey, cfg: &Config, wallet: &Wallet, since: i64) -> SellOutcome {
+    let reason = match find_sale(mint, cfg, wallet, since).await {
+        Ok(Some(outcome)) => return outcome,
+        Ok(None) => "its transaction wasn't found".to_string(),
+        Err(e) => format!("looking it up failed: {}", e),
+    };
+    let msg = format!("🚨 An earlier sell of {} from {} landed unconfirmed and {}; its PnL isn't booked", mint, wallet.name, reason);
+    tracing::error!("{}", msg);
+    crate::notifier::log(msg).await;
+    SellOutcome { tokens_sold: 0, transfer_fee: 0, expected_sol: None, sol_received: 0.0 }
+}
+
+/// Sell until the order fills, escalating slippage and fees and switching venues as attempts fail.
+/// An exit is never given up on; it alerts instead so someone can step in.
+pub async fn sell_until_filled(mint: Pubkey, sell_amount: SellAmount, cfg: &Config, wallet: &Wallet, db: &ProfitDatabase) -> SellOutcome {
+    let exit = &cfg.exit;
+    let mut failures: u32 = 0;
+    // Partial sells are pinned to raw tokens up front; re-resolving a percent on retry would sell
+    // a further share of the remainder if an earlier attempt landed but its confirmation was lost
+    let mut pinned: Option<(u64, u64)> = None;
+    let started = unix_now() - BLOCK_TIME_SLACK_SECS;
+    loop {
+        let attempt_cfg = crate::retry::escalate(cfg, failures, exit.slippage_step_bps, exit.max_slippage_bps, exit.fee_multiplier, exit.max_priority_fee_microlamports);
+        let venue = exit.venue(failures);
+        let amount = if sell_amount == SellAmount::All {
+            Ok(SellAmount::All)
+        } else {
+            held(&mint, cfg, wallet, db).await.map(|balance| match pinned {
+                None => {
+                    let (keep, amount) = pin(sell_amount, balance);
+                    pinned = Some((keep, amount));
+                    SellAmount::Raw(amount)
+                }
+                Some((keep, _)) => SellAmount::Raw(balance.saturating_sub(keep)),
+            })
+        };
+        let result = match amount {
+            Ok(SellAmount::Raw(0)) if pinned.is_some_and(|(_, amount)| amount > 0) => {
+                return landed_unconfirmed(&mint, cfg, wallet, started).await;
+            }
+            Ok(amount) => crate::sell::execute_via(mint, amount, attempt_cfg.clone(), wallet.clone(), db.clone(), venue).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            // Nothing left to sell after a failed attempt: that attempt went through after all
+            Ok(outcome) if outcome.tokens_sold == 0 && failures > 0 && !cfg.is_paper() => {
+                return landed_unconfirmed(&mint, cfg, wallet, started).await;
+            }
+            Ok(outcome) => {
+                if failures > 0 {
+                    crate::notifier::log(format!("✅ Exit of {} from {} filled via {} after {} failed attempt(s)", mint, wallet.name, venue, failures)).await;
+                }
+                return outcome;
+            }
+            Err(e) => {
+                failures += 1;
+                tracing::warn!("Sell {} of {} via {} failed (attempt {}, {} bps, {} µlamports/CU): {}",
+                    sell_amount, mint, venue, failures, attempt_cfg.slippage_bps, attempt_cfg.priority_fee_microlamports, e);
+                if exit.should_alert(failures) {
+                    crate::notifier::log(format!("🚨 EXIT STUCK: selling {} of {} from {} has failed {} times, still retrying. Last error: {}",
+                        sell_amount, mint, wallet.name, failures, e)).await;
+                }
+                tokio::time::sleep(exit.backoff(failures)).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_venue_alert_and_backoff_schedule() {
+        let exit = ExitConfig::default();
+        let venues: Vec<Venue> = (0..6).map(|failures| exit.venue(failures)).collect();
+        assert_eq!(venues, [Venue::Jupiter, Venue::Jupiter, Venue::PumpPortal, Venue::Jupiter, Venue::PumpPortal, Venue::Jupiter]);
+        let no_fallback = ExitConfig { pumpportal_fallback: false, ..Default::default() };
+        assert!((0..6).all(|failures| no_fallback.venue(failures) == Venue::Jupiter));
+
+        assert!(!exit.should_alert(2));
+        assert!(exit.should_alert(3) && exit.should_alert(6));
+
+        assert_eq!(exit.backoff(1), Duration::from_millis(500));
+        assert_eq!(exit.backoff(3), Duration::from_millis(2_000));
+        assert_eq!(exit.backoff(40), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_exit_escalation_caps() {
+        let base = crate::test_config();
+        let exit = ExitConfig::default();
+        let late = crate::retry::escalate(&base, 1_000, exit.slippage_step_bps, exit.max_slippage_bps, exit.fee_multiplier, exit.max_priority_fee_microlamports);
+        assert_eq!((late.slippage_bps, late.priority_fee_microlamports), (5_000, 5_000_000));
+    }
+
+    #[test]
+    fn test_pin_partial_sells() {
+        assert_eq!(pin(SellAmount::Percent(50), 1_001), (501, 500));
+        assert_eq!(pin(SellAmount::Raw(300), 1_000), (700, 300));
+        assert_eq!(pin(SellAmount::Raw(3_000), 1_000), (0, 1_000));
+    }
+}
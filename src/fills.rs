@@ -0,0 +1,261 @@
+use anyhow::{anyhow, Result};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::{UiTransactionEncoding, UiTransactionStatusMeta, UiTransactionTokenBalance};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Attempts to fetch a just-confirmed transaction before giving up
+const FETCH_ATTEMPTS: u32 = 5;
+
+/// What a confirmed swap actually cost and delivered, read from the transaction meta
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fill {
+    /// Drop in the payer's SOL balance: swap input plus fees and rent
+    pub sol_spent: u64,
+    /// Network and priority fee paid
+    pub fee: u64,
+    /// Lamports left behind in accounts the transaction created (e.g. the token account)
+    pub rent: u64,
+    /// Raw tokens that landed in the payer's token accounts
+    pub tokens: u64,
+}
+
+impl Fill {
+    pub fn sol_spent_f64(&self) -> f64 {
+        self.sol_spent as f64 / 1e9
+    }
+
+    /// Lamports paid per raw token, or 0 when nothing was received
+    pub fn price(&self) -> f64 {
+        if self.tokens == 0 {
+            return 0.0;
+        }
+        self.sol_spent as f64 / self.tokens as f64
+    }
+
+    /// SOL paid per whole token, fees included and refundable rent left out
+    pub fn entry_price(&self, decimals: u8) -> Option<f64> {
+        if self.tokens == 0 {
+            return None;
+        }
+        let paid = self.sol_spent.saturating_sub(self.rent) as f64 / 1e9;
+        Some(paid / (self.tokens as f64 / 10f64.powi(decimals as i32)))
+    }
+}
+
+/// Raw `mint` balance per token account owned by `owner`
+fn owned_balances(balances: &OptionSerializer<Vec<UiTransactionTokenBalance>>, owner: &str, mint: &str) -> HashMap<u8, u64> {
+    let OptionSerializer::Some(balances) = balances else {
+        return HashMap::new();
+    };
+    balances
+        .iter()
+        .filter(|b| b.mint == mint && matches!(&b.owner, OptionSerializer::Some(o) if o == owner))
+        .map(|b| (b.account_index, b.ui_token_amount.amount.parse().unwrap_or(0)))
+        .collect()
+}
+
+/// Work out a buy fill for `payer` from transaction meta; the payer is always account 0
+pub fn parse_fill(meta: &UiTransactionStatusMeta, payer: &Pubkey, mint: &Pubkey) -> Result<Fill> {
+    if let Some(err) = &meta.err {
+        return Err(anyhow!("Transaction failed: {:?}", err));
+    }
+    let pre_sol = *meta.pre_balances.first().ok_or(anyhow!("Transaction meta has no balances"))?;
+    let post_sol = *meta.post_balances.first().ok_or(anyhow!("Transaction meta has no balances"))?;
+    // Accounts that went from empty to funded were created here and hold rent
+    let rent = meta
+        .pre_balances
+        .iter()
+        .zip(&meta.post_balances)
+        .skip(1)
+        .filter(|(pre, _)| **pre == 0)
+        .map(|(_, post)| *post)
+        .sum();
+
+    let (owner, mint) = (payer.to_string(), mint.to_string());
+    let pre_tokens = owned_balances(&meta.pre_token_balances, &owner, &mint);
+    // A token account created by this transaction has no pre balance entry
+    let tokens = owned_balances(&meta.post_token_balances, &owner, &mint)
+        .into_iter()
+        .map(|(index, post)| post.saturating_sub(pre_tokens.get(&index).copied().unwrap_or(0)))
+        .sum();
+
+    Ok(Fill { sol_spent: pre_sol.saturating_sub(post_sol), fee: meta.fee, rent, tokens })
+}
+
+/// What a confirmed sell paid out, read from the transaction meta
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sale {
+    /// Change in the payer's SOL balance, net of fees; only this transaction's, unlike a wallet balance read
+    pub sol_received: i64,
+    /// Raw tokens that left the payer's token accounts
+    pub tokens: u64,
+}
+
+impl Sale {
+    pub fn sol_received_f64(&self) -> f64 {
+        self.sol_received as f64 / 1e9
+    }
+}
+
+/// Work out a sale for `payer` from transaction meta; the payer is always account 0
+pub fn parse_sale(meta: &UiTransactionStatusMeta, payer: &Pubkey, mint: &Pubkey) -> Result<Sale> {
+    if let Some(err) = &meta.err {
+        return Err(anyhow!("Transaction failed: {:?}", err));
+    }
+    let pre_sol = *meta.pre_balances.first().ok_or(anyhow!("Transaction meta has no balances"))?;
+    let post_sol = *meta.post_balances.first().ok_or(anyhow!("Transaction meta has no balances"))?;
+    let (owner, mint) = (payer.to_string(), mint.to_string());
+    let post_tokens = owned_balances(&meta.post_token_balances, &owner, &mint);
+    // A token account closed by this transaction has no post balance entry
+    let tokens = owned_balances(&meta.pre_token_balances, &owner, &mint)
+        .into_iter()
+        .map(|(index, pre)| pre.saturating_sub(post_tokens.get(&index).copied().unwrap_or(0)))
+        .sum();
+    Ok(Sale { sol_received: post_sol as i64 - pre_sol as i64, tokens })
+}
+
+/// Fetch a confirmed transaction's meta, retrying while the node catches up
+async fn fetch_meta(rpc: &RpcClient, signature: &Signature) -> Result<UiTransactionStatusMeta> {
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(rpc.commitment()),
+        max_supported_transaction_version: Some(0),
+    };
+    let mut last_err = anyhow!("Transaction {} not found", signature);
+    for attempt in 0..FETCH_ATTEMPTS {
+        if attempt > 0 {
+            tokio::time::sleep(Duration::from_millis(500 * attempt as u64)).await;
+        }
+        match rpc.get_transaction_with_config(signature, config).await {
+            Ok(tx) => return tx.transaction.meta.ok_or(anyhow!("Transaction {} has no meta", signature)),
+            Err(e) => last_err = e.into(),
+        }
+    }
+    Err(last_err)
+}
+
+/// Fetch a confirmed buy and parse its fill
+pub async fn fetch_fill(rpc: &RpcClient, signature: &Signature, payer: &Pubkey, mint: &Pubkey) -> Result<Fill> {
+    parse_fill(&fetch_meta(rpc, signature).await?, payer, mint)
+}
+
+/// Fetch a confirmed sell and parse what it paid out
+pub async fn fetch_sale(rpc: &RpcClient, signature: &Signature, payer: &Pubkey, mint: &Pubkey) -> Result<Sale> {
+    parse_sale(&fetch_meta(rpc, signature).await?, payer, mint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn token_balance(index: u8, mint: &Pubkey, owner: &Pubkey, amount: u64) -> serde_json::Value {
+        json!({
+            "accountIndex": index,
+            "mint": mint.to_string(),
+            "owner": owner.to_string(),
+            "programId": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+            "uiTokenAmount": {
+                "amount": amount.to_string(),
+                "decimals": 6,
+                "uiAmount": amount as f64 / 1e6,
+                "uiAmountString": (amount as f64 / 1e6).to_string(),
+            },
+        })
+    }
+
+    #[test]
+    fn test_parse_fill_with_new_token_account() {
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let pool = Pubkey::new_unique();
+        // Payer, new ATA, pool vault; the ATA has no pre token balance
+        let meta: UiTransactionStatusMeta = serde_json::from_value(json!({
+            "err": null,
+            "status": { "Ok": null },
+            "fee": 105_000,
+            "preBalances": [2_000_000_000u64, 0, 50_000_000_000u64],
+            "postBalances": [1_897_855_720u64, 2_039_280, 50_100_000_000u64],
+            "preTokenBalances": [token_balance(2, &mint, &pool, 900_000_000)],
+            "postTokenBalances": [
+                token_balance(1, &mint, &payer, 1_234_567),
+                token_balance(2, &mint, &pool, 898_765_433),
+            ],
+        }))
+        .unwrap();
+
+        let fill = parse_fill(&meta, &payer, &mint).unwrap();
+        assert_eq!(fill, Fill { sol_spent: 102_144_280, fee: 105_000, rent: 2_039_280, tokens: 1_234_567 });
+        assert_eq!(fill.sol_spent, 100_000_000 + fill.fee + fill.rent);
+        // 0.100105 SOL for 1.234567 six-decimal tokens
+        assert!((fill.entry_price(6).unwrap() - 0.100105 / 1.234567).abs() < 1e-12);
+        assert_eq!(Fill { tokens: 0, ..fill }.entry_price(6), None);
+    }
+
+    #[test]
+    fn test_parse_fill_existing_account_and_failed_tx() {
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let meta: UiTransactionStatusMeta = serde_json::from_value(json!({
+            "err": null,
+            "status": { "Ok": null },
+            "fee": 5_000,
+            "preBalances": [1_000_000_000u64, 2_039_280],
+            "postBalances": [949_995_000u64, 2_039_280],
+            "preTokenBalances": [token_balance(1, &mint, &payer, 500)],
+            "postTokenBalances": [token_balance(1, &mint, &payer, 1_500)],
+        }))
+        .unwrap();
+        let fill = parse_fill(&meta, &payer, &mint).unwrap();
+        assert_eq!(fill, Fill { sol_spent: 50_005_000, fee: 5_000, rent: 0, tokens: 1_000 });
+
+        let failed: UiTransactionStatusMeta = serde_json::from_value(json!({
+            "err": { "InstructionError": [2, { "Custom": 6001 }] },
+            "status": { "Err": { "InstructionError": [2, { "Custom": 6001 }] } },
+            "fee": 5_000,
+            "preBalances": [1_000_000_000u64],
+            "postBalances": [999_995_000u64],
+        }))
+        .unwrap();
+        assert!(parse_fill(&failed, &payer, &mint).is_err());
+        assert!(parse_sale(&failed, &payer, &mint).is_err());
+    }
+
+    #[test]
+    fn test_parse_sale_closing_token_account() {
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let pool = Pubkey::new_unique();
+        // Payer, its token account (closed by the sell, rent refunded), pool vault
+        let meta: UiTransactionStatusMeta = serde_json::from_value(json!({
+            "err": null,
+            "status": { "Ok": null },
+            "fee": 5_000,
+            "preBalances": [1_000_000_000u64, 2_039_280, 50_000_000_000u64],
+            "postBalances": [1_052_034_280u64, 0, 49_950_000_000u64],
+            "preTokenBalances": [token_balance(1, &mint, &payer, 1_000), token_balance(2, &mint, &pool, 9_000)],
+            "postTokenBalances": [token_balance(2, &mint, &pool, 10_000)],
+        }))
+        .unwrap();
+        let sale = parse_sale(&meta, &payer, &mint).unwrap();
+        assert_eq!(sale, Sale { sol_received: 50_000_000 + 2_039_280 - 5_000, tokens: 1_000 });
+
+        // A sell that cost more in fees than it brought in
+        let dust: UiTransactionStatusMeta = serde_json::from_value(json!({
+            "err": null,
+            "status": { "Ok": null },
+            "fee": 5_000,
+            "preBalances": [1_000_000_000u64],
+            "postBalances": [999_996_000u64],
+            "preTokenBalances": [token_balance(0, &mint, &payer, 10)],
+            "postTokenBalances": [token_balance(0, &mint, &payer, 0)],
+        }))
+        .unwrap();
+        assert_eq!(parse_sale(&dust, &payer, &mint).unwrap(), Sale { sol_received: -4_000, tokens: 10 });
+    }
+}
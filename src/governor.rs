@@ -0,0 +1,292 @@
+use serde::Deserialize;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::profit_db::ProfitDatabase;
+
+/// Loss limits that pause new buys (`[governor]` table in config.toml)
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct GovernorConfig {
+    pub enabled: bool,
+    /// Realized loss for the UTC day, in SOL, that pauses buying
+    pub daily_loss_limit_sol: Option<f64>,
+    /// Drop from the day's best realized PnL, in SOL, that pauses buying once the day has been in profit
+    pub max_drawdown_sol: Option<f64>,
+    /// Losing exits in a row that pause buying; the streak carries over midnight
+    pub max_loss_streak: Option<u32>,
+    pub check_interval_secs: u64,
+}
+
+impl Default for GovernorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            daily_loss_limit_sol: None,
+            max_drawdown_sol: None,
+            max_loss_streak: None,
+            check_interval_secs: 30,
+        }
+    }
+}
+
+/// Why new buys are paused
+#[derive(Debug, Clone, PartialEq)]
+pub enum Halt {
+    Manual,
+    DailyLoss { pnl: f64, limit: f64 },
+    Drawdown { drawdown: f64, limit: f64 },
+    LossStreak(u32),
+}
+
+impl fmt::Display for Halt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Halt::Manual => write!(f, "paused manually"),
+            Halt::DailyLoss { pnl, limit } => write!(f, "daily loss {:.4} SOL hit the {:.4} SOL limit", pnl, limit),
+            Halt::Drawdown { drawdown, limit } => write!(f, "drawdown {:.4} SOL from today's peak hit the {:.4} SOL limit", drawdown, limit),
+            Halt::LossStreak(n) => write!(f, "{} losing trades in a row", n),
+        }
+    }
+}
+
+impl std::error::Error for Halt {}
+
+/// Losing trades in a row counting back from the latest; `recent` is newest first
+pub fn loss_streak(recent: &[f64]) -> u32 {
+    recent.iter().take_while(|pnl| **pnl < 0.0).count() as u32
+}
+
+/// Check the day's realized PnL, oldest trade first, and the current losing streak against the limits
+pub fn evaluate(config: &GovernorConfig, pnls: &[f64], streak: u32) -> Option<Halt> {
+    let mut total = 0.0_f64;
+    // Losses before the day has been in profit are the daily limit's business, not a drawdown
+    let mut peak = 0.0_f64;
+    for pnl in pnls {
+        total += pnl;
+        peak = peak.max(total);
+    }
+    if let Some(limit) = config.daily_loss_limit_sol {
+        if -total >= limit {
+            return Some(Halt::DailyLoss { pnl: total, limit });
+        }
+    }
+    if let Some(limit) = config.max_drawdown_sol {
+        if peak > 0.0 && peak - total >= limit {
+            return Some(Halt::Drawdown { drawdown: peak - total, limit });
+        }
+    }
+    if let Some(max) = config.max_loss_streak {
+        if max > 0 && streak >= max {
+            return Some(Halt::LossStreak(streak));
+        }
+    }
+    None
+}
+
+fn utc_day() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / 86_400
+}
+
+/// Flag in the database holding a manual pause, so it survives restarts and reaches a running bot
+const PAUSE_FLAG: &str = "buys_paused";
+
+#[derive(Default)]
+struct GovernorState {
+    tripped: Option<Halt>,
+    /// Trades up to this id are ignored after a manual resume: for the day's limits until
+    /// midnight, and for the losing streak from then on
+    baseline: Option<(u64, i64)>,
+}
+
+/// Pauses new buys on a manual stop, when today's losses hit a limit or after a losing streak; exits keep running
+pub struct RiskGovernor {
+    config: GovernorConfig,
+    paper: bool,
+    db: ProfitDatabase,
+    state: Mutex<GovernorState>,
+}
+
+impl RiskGovernor {
+    pub fn new(config: GovernorConfig, paper: bool, db: ProfitDatabase) -> Self {
+        Self { config, paper, db, state: Mutex::new(GovernorState::default()) }
+    }
+
+    /// Today's (id, pnl) trades that count towards the limits
+    fn todays_trades(&self) -> Vec<(i64, f64)> {
+        let after = match self.state.lock().unwrap().baseline {
+            Some((day, id)) if day == utc_day() => id,
+            _ => 0,
+        };
+        self.db.todays_trades(self.paper, after).unwrap_or_else(|e| {
+            tracing::warn!("Could not load today's trades for the risk governor: {}", e);
+            Vec::new()
+        })
+    }
+
+    /// The losing streak since the last manual resume, looking back no further than the limit needs
+    fn loss_streak(&self) -> u32 {
+        let Some(max) = self.config.max_loss_streak else {
+            return 0;
+        };
+        let after = self.state.lock().unwrap().baseline.map_or(0, |(_, id)| id);
+        match self.db.recent_trades(self.paper, after, max) {
+            Ok(trades) => loss_streak(&trades.into_iter().map(|(_, pnl)| pnl).collect::<Vec<_>>()),
+            Err(e) => {
+                tracing::warn!("Could not load recent trades for the risk governor: {}", e);
+                0
+            }
+        }
+    }
+
+    /// Re-evaluate the limits, with a notice when the pause state changed
+    fn refresh(&self) -> (Result<(), Halt>, Option<String>) {
+        if self.manual() {
+            return (Err(Halt::Manual), None);
+        }
+        if !self.config.enabled {
+            return (Ok(()), None);
+        }
+        let pnls: Vec<f64> = self.todays_trades().into_iter().map(|(_, pnl)| pnl).collect();
+        let halt = evaluate(&self.config, &pnls, self.loss_streak());
+        let previous = std::mem::replace(&mut self.state.lock().unwrap().tripped, halt.clone());
+        let notice = match (&previous, &halt) {
+            (None, Some(halt)) => {
+                // A losing streak doesn't end at midnight
+                let resumes = if matches!(halt, Halt::LossStreak(_)) { "resumes with /resume" } else { "resumes at 00:00 UTC or with /resume" };
+                Some(format!("🧯 New buys paused: {}. Open positions are still managed; {}", halt, resumes))
+            }
+            (Some(_), None) => Some("✅ Loss limits clear, new buys resumed".to_string()),
+            _ => None,
+        };
+        (halt.map_or(Ok(()), Err), notice)
+    }
+
+    /// Whether new buys may go ahead; notifies when a limit trips or clears
+    pub async fn check(&self) -> Result<(), Halt> {
+        let (result, notice) = self.refresh();
+        if let Some(notice) = notice {
+            tracing::warn!("{}", notice);
+            crate::notifier::log(notice).await;
+        }
+        result
+    }
+
+    fn manual(&self) -> bool {
+        self.db.flag(PAUSE_FLAG).unwrap_or_else(|e| {
+            tracing::warn!("Could not read the buy pause flag: {}", e);
+            false
+        })
+    }
+
+    fn set_manual(&self, paused: bool) {
+        if let Err(e) = self.db.set_flag(PAUSE_FLAG, paused) {
+            tracing::error!("Could not save the buy pause flag: {}", e);
+        }
+    }
+
+    /// Stop new buys until `resume`, including in other processes sharing the database
+    pub fn pause(&self) {
+        self.set_manual(true);
+    }
+
+    /// Lift a manual pause and any tripped limit; trades so far stop counting
+    pub fn resume(&self) {
+        let last_id = match self.db.recent_trades(self.paper, 0, 1) {
+            Ok(trades) => trades.first().map(|(id, _)| *id),
+            Err(e) => {
+                tracing::warn!("Could not load the last trade for the risk governor: {}", e);
+                None
+            }
+        };
+        self.set_manual(false);
+        let mut state = self.state.lock().unwrap();
+        state.tripped = None;
+        if let Some(id) = last_id {
+            state.baseline = Some((utc_day(), id));
+        }
+    }
+
+    pub fn status(&self) -> Option<Halt> {
+        if self.manual() {
+            return Some(Halt::Manual);
+        }
+        self.state.lock().unwrap().tripped.clone()
+    }
+
+    /// Re-check the limits periodically so a trip is reported without waiting for a signal
+    pub async fn watch(&self) {
+        if !self.config.enabled {
+            return std::future::pending().await;
+        }
+        let mut interval = tokio::time::interval(Duration::from_secs(self.config.check_interval_secs.max(1)));
+        loop {
+            interval.tick().await;
+            let _ = self.check().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profit_db::TradeRecord;
+
+    const LOSS: TradeRecord = TradeRecord { wallet: "main", mint: "mint", source: "test", cost_sol: 0.1, pnl: -0.05, paper: false, position_id: None };
+
+    fn config() -> GovernorConfig {
+        GovernorConfig {
+            enabled: true,
+            daily_loss_limit_sol: Some(1.0),
+            max_drawdown_sol: Some(0.8),
+            max_loss_streak: Some(3),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_evaluate_limits() {
+        let config = config();
+        assert_eq!(evaluate(&config, &[], 0), None);
+        assert_eq!(evaluate(&config, &[0.5, -0.2, 0.1, -0.1], 1), None);
+        assert!(matches!(evaluate(&config, &[-0.7, 0.2, -0.6], 1), Some(Halt::DailyLoss { .. })));
+        // Up 1.0 then down 0.9: still green on the day but off the peak
+        assert!(matches!(evaluate(&config, &[1.0, -0.9], 1), Some(Halt::Drawdown { .. })));
+        // Losses before any profit aren't a drawdown
+        assert_eq!(evaluate(&config, &[-0.5, -0.4], 0), None);
+        assert_eq!(evaluate(&config, &[0.3, -0.05, -0.05, -0.05], 3), Some(Halt::LossStreak(3)));
+
+        assert_eq!(loss_streak(&[-0.05, -0.05, 0.01, -0.05]), 2);
+        assert_eq!(loss_streak(&[0.01, -0.05]), 0);
+    }
+
+    #[test]
+    fn test_pause_and_resume() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = ProfitDatabase::new(dir.path().join("profit.db").to_str().unwrap()).unwrap();
+        let governor = RiskGovernor::new(config(), false, db.clone());
+        assert_eq!(governor.refresh(), (Ok(()), None));
+
+        for _ in 0..3 {
+            db.record_trade(&LOSS).unwrap();
+        }
+        let (result, notice) = governor.refresh();
+        assert_eq!(result, Err(Halt::LossStreak(3)));
+        assert!(notice.unwrap().contains("3 losing trades"));
+        assert_eq!(governor.status(), Some(Halt::LossStreak(3)));
+
+        // Resuming forgives the trades so far, but new losses still count
+        governor.resume();
+        assert_eq!(governor.refresh().0, Ok(()));
+        governor.pause();
+        assert_eq!(governor.refresh().0, Err(Halt::Manual));
+        // Another process sharing the database sees the pause
+        assert_eq!(RiskGovernor::new(config(), false, db.clone()).status(), Some(Halt::Manual));
+        governor.resume();
+        db.record_trade(&LOSS).unwrap();
+        assert_eq!(governor.refresh().0, Ok(()));
+        db.record_trade(&LOSS).unwrap();
+        db.record_trade(&LOSS).unwrap();
+        assert_eq!(governor.refresh().0, Err(Halt::LossStreak(3)));
+    }
+}
@@ -0,0 +1,157 @@
+use serde::Deserialize;
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use std::fmt;
+
+/// Quote sanity guards (`[guards]` table in config.toml)
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct QuoteGuards {
+    /// Reject quotes whose price impact exceeds this percent
+    pub max_price_impact_pct: Option<f64>,
+    /// Reject when the worst-case output (`otherAmountThreshold`) is more than this
+    /// percent below what the reference price says we should get
+    pub max_shortfall_pct: Option<f64>,
+    /// AMM labels (e.g. "Whirlpool") or AMM keys we never route through
+    pub banned_amms: Vec<String>,
+    /// Mints we never route through as an intermediate hop
+    pub banned_intermediate_mints: Vec<String>,
+    /// Also guard sells; off by default so exits are never blocked
+    pub apply_to_sells: bool,
+}
+
+/// Which guard rejected a quote
+#[derive(Debug, Clone, PartialEq)]
+pub enum GuardViolation {
+    PriceImpact { impact_pct: f64, max_pct: f64 },
+    Shortfall { min_out: u64, reference_out: u64, shortfall_pct: f64, max_pct: f64 },
+    BannedAmm { label: String, amm_key: String },
+    BannedIntermediate { mint: String },
+}
+
+impl fmt::Display for GuardViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GuardViolation::PriceImpact { impact_pct, max_pct } => {
+                write!(f, "price impact guard: {:.2}% > max {:.2}%", impact_pct, max_pct)
+            }
+            GuardViolation::Shortfall { min_out, reference_out, shortfall_pct, max_pct } => write!(
+                f,
+                "min output guard: worst case {} is {:.2}% below reference {} (max {:.2}%)",
+                min_out, shortfall_pct, reference_out, max_pct
+            ),
+            GuardViolation::BannedAmm { label, amm_key } => {
+                write!(f, "route guard: banned AMM {} ({})", label, amm_key)
+            }
+            GuardViolation::BannedIntermediate { mint } => {
+                write!(f, "route guard: banned intermediate token {}", mint)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GuardViolation {}
+
+fn amount(quote: &Value, key: &str) -> Option<u64> {
+    quote[key].as_str().and_then(|v| v.parse().ok())
+}
+
+/// Jupiter's `priceImpactPct` is a fraction (0.01 = 1%); returns it as a percent
+pub fn price_impact_percent(quote: &Value) -> f64 {
+    quote["priceImpactPct"].as_str().and_then(|v| v.parse::<f64>().ok()).unwrap_or(0.0) * 100.0
+}
+
+/// Run every configured guard over a quote
+pub fn check_quote(
+    guards: &QuoteGuards,
+    quote: &Value,
+    input_mint: &Pubkey,
+    output_mint: &Pubkey,
+    reference_out: Option<u64>,
+) -> Result<(), GuardViolation> {
+    if let Some(max_pct) = guards.max_price_impact_pct {
+        let impact_pct = price_impact_percent(quote);
+        if impact_pct > max_pct {
+            return Err(GuardViolation::PriceImpact { impact_pct, max_pct });
+        }
+    }
+
+    if let (Some(max_pct), Some(reference_out)) = (guards.max_shortfall_pct, reference_out) {
+        let min_out = amount(quote, "otherAmountThreshold").or_else(|| amount(quote, "outAmount")).unwrap_or(0);
+        if reference_out > 0 {
+            let shortfall_pct = (1.0 - min_out as f64 / reference_out as f64) * 100.0;
+            if shortfall_pct > max_pct {
+                return Err(GuardViolation::Shortfall { min_out, reference_out, shortfall_pct, max_pct });
+            }
+        }
+    }
+
+    let endpoints = [input_mint.to_string(), output_mint.to_string()];
+    for step in quote["routePlan"].as_array().into_iter().flatten() {
+        let info = &step["swapInfo"];
+        let label = info["label"].as_str().unwrap_or_default();
+        let amm_key = info["ammKey"].as_str().unwrap_or_default();
+        if guards.banned_amms.iter().any(|b| b.eq_ignore_ascii_case(label) || b == amm_key) {
+            return Err(GuardViolation::BannedAmm { label: label.to_string(), amm_key: amm_key.to_string() });
+        }
+        for key in ["inputMint", "outputMint"] {
+            let mint = info[key].as_str().unwrap_or_default();
+            if !endpoints.iter().any(|e| e == mint) && guards.banned_intermediate_mints.iter().any(|b| b == mint) {
+                return Err(GuardViolation::BannedIntermediate { mint: mint.to_string() });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn quote(input: &Pubkey, mid: &Pubkey, output: &Pubkey) -> Value {
+        json!({
+            "outAmount": "1000",
+            "otherAmountThreshold": "950",
+            "priceImpactPct": "0.031",
+            "routePlan": [
+                { "swapInfo": { "ammKey": "AmmOne", "label": "Raydium", "inputMint": input.to_string(), "outputMint": mid.to_string() } },
+                { "swapInfo": { "ammKey": "AmmTwo", "label": "Whirlpool", "inputMint": mid.to_string(), "outputMint": output.to_string() } }
+            ]
+        })
+    }
+
+    #[test]
+    fn test_guards_pass_when_unconfigured() {
+        let (a, b, c) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        assert!(check_quote(&QuoteGuards::default(), &quote(&a, &b, &c), &a, &c, Some(10_000)).is_ok());
+    }
+
+    #[test]
+    fn test_each_guard_names_itself() {
+        let (a, b, c) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let q = quote(&a, &b, &c);
+
+        let guards = QuoteGuards { max_price_impact_pct: Some(2.0), ..Default::default() };
+        let err = check_quote(&guards, &q, &a, &c, None).unwrap_err();
+        assert!(matches!(err, GuardViolation::PriceImpact { .. }));
+        assert!(err.to_string().starts_with("price impact guard"));
+
+        let guards = QuoteGuards { max_shortfall_pct: Some(10.0), ..Default::default() };
+        assert!(check_quote(&guards, &q, &a, &c, Some(1_000)).is_ok());
+        let err = check_quote(&guards, &q, &a, &c, Some(2_000)).unwrap_err();
+        assert!(matches!(err, GuardViolation::Shortfall { min_out: 950, reference_out: 2_000, .. }));
+
+        let guards = QuoteGuards { banned_amms: vec!["whirlpool".to_string()], ..Default::default() };
+        let err = check_quote(&guards, &q, &a, &c, None).unwrap_err();
+        assert_eq!(err, GuardViolation::BannedAmm { label: "Whirlpool".to_string(), amm_key: "AmmTwo".to_string() });
+
+        // Banning an endpoint mint doesn't count as an intermediate hop
+        let guards = QuoteGuards { banned_intermediate_mints: vec![a.to_string()], ..Default::default() };
+        assert!(check_quote(&guards, &q, &a, &c, None).is_ok());
+        let guards = QuoteGuards { banned_intermediate_mints: vec![b.to_string()], ..Default::default() };
+        let err = check_quote(&guards, &q, &a, &c, None).unwrap_err();
+        assert_eq!(err, GuardViolation::BannedIntermediate { mint: b.to_string() });
+    }
+}
@@ -0,0 +1,318 @@
+use anyhow::{anyhow, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use solana_sdk::derivation_path::DerivationPath;
+use solana_sdk::signer::keypair::{keypair_from_seed_and_derivation_path, read_keypair, read_keypair_file, Keypair};
+use solana_sdk::signer::Signer;
+use std::path::Path;
+use zeroize::Zeroizing;
+
+/// Passphrase given directly in the environment
+pub const PASSPHRASE_ENV: &str = "SNIPE_KEYSTORE_PASSPHRASE";
+/// File descriptor to read the passphrase from, e.g. `3<secret.txt`
+pub const PASSPHRASE_FD_ENV: &str = "SNIPE_KEYSTORE_PASSPHRASE_FD";
+/// Phantom / Solflare default account
+pub const DEFAULT_DERIVATION_PATH: &str = "m/44'/501'/0'/0'";
+
+const VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+
+/// Argon2id cost parameters
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KdfParams {
+    /// Memory in KiB
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self { m_cost: 64 * 1024, t_cost: 3, p_cost: 1 }
+    }
+}
+
+/// On-disk keystore: the 64-byte keypair sealed with XChaCha20-Poly1305 under an Argon2id key.
+/// The public key is bound in as associated data so it can't be swapped.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Keystore {
+    pub version: u8,
+    pub pubkey: String,
+    pub kdf: KdfParams,
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], kdf: &KdfParams) -> Result<Zeroizing<[u8; 32]>> {
+    let params = Params::new(kdf.m_cost, kdf.t_cost, kdf.p_cost, Some(32)).map_err(|e| anyhow!("Bad KDF params: {}", e))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key[..])
+        .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+fn b64_decode(field: &str, value: &str) -> Result<Vec<u8>> {
+    general_purpose::STANDARD.decode(value).map_err(|e| anyhow!("Bad {} in keystore: {}", field, e))
+}
+
+impl Keystore {
+    pub fn encrypt(keypair: &Keypair, passphrase: &str, kdf: KdfParams) -> Result<Self> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let key = derive_key(passphrase, &salt, &kdf)?;
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(&key[..]));
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let secret = Zeroizing::new(keypair.to_bytes());
+        let pubkey = keypair.pubkey();
+        let ciphertext = cipher
+            .encrypt(&nonce, Payload { msg: &secret[..], aad: pubkey.as_ref() })
+            .map_err(|_| anyhow!("Encryption failed"))?;
+        Ok(Self {
+            version: VERSION,
+            pubkey: pubkey.to_string(),
+            kdf,
+            salt: general_purpose::STANDARD.encode(salt),
+            nonce: general_purpose::STANDARD.encode(nonce),
+            ciphertext: general_purpose::STANDARD.encode(ciphertext),
+        })
+    }
+
+    pub fn decrypt(&self, passphrase: &str) -> Result<Keypair> {
+        if self.version != VERSION {
+            return Err(anyhow!("Unsupported keystore version {}", self.version));
+        }
+        let pubkey: solana_sdk::pubkey::Pubkey = self.pubkey.parse()?;
+        let key = derive_key(passphrase, &b64_decode("salt", &self.salt)?, &self.kdf)?;
+        let nonce = b64_decode("nonce", &self.nonce)?;
+        if nonce.len() != 24 {
+            return Err(anyhow!("Bad nonce length in keystore"));
+        }
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(&key[..]));
+        let secret = Zeroizing::new(
+            cipher
+                .decrypt(XNonce::from_slice(&nonce), Payload { msg: &b64_decode("ciphertext", &self.ciphertext)?, aad: pubkey.as_ref() })
+                .map_err(|_| anyhow!("Wrong passphrase or corrupted keystore"))?,
+        );
+        let keypair = Keypair::try_from(secret.as_slice()).map_err(|e| anyhow!("Bad keypair in keystore: {}", e))?;
+        if keypair.pubkey() != pubkey {
+            return Err(anyhow!("Keystore public key does not match its secret"));
+        }
+        Ok(keypair)
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// Create `path` readable by the owner only; never overwrites an existing file
+    pub fn write(&self, path: &Path) -> Result<()> {
+        use std::io::Write;
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::AlreadyExists => anyhow!("{} already exists", path.display()),
+            _ => anyhow!("Could not create {}: {}", path.display(), e),
+        })?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Read a passphrase from `SNIPE_KEYSTORE_PASSPHRASE`, `SNIPE_KEYSTORE_PASSPHRASE_FD`, or the terminal.
+/// The environment variable is removed once read so child processes don't inherit it.
+pub fn read_passphrase(prompt: &str) -> Result<Zeroizing<String>> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        // Only called while loading keys at startup, before any other threads read the environment
+        std::env::remove_var(PASSPHRASE_ENV);
+        return Ok(Zeroizing::new(passphrase));
+    }
+    if let Ok(fd) = std::env::var(PASSPHRASE_FD_ENV) {
+        return read_passphrase_fd(fd.trim().parse().map_err(|e| anyhow!("Bad {}: {}", PASSPHRASE_FD_ENV, e))?);
+    }
+    Ok(Zeroizing::new(rpassword::prompt_password(prompt)?))
+}
+
+#[cfg(unix)]
+fn read_passphrase_fd(fd: i32) -> Result<Zeroizing<String>> {
+    use std::io::Read;
+    use std::os::fd::FromRawFd;
+    // Taking ownership of stdin, stdout or stderr would close it when we're done
+    if fd <= 2 {
+        return Err(anyhow!("{} must be a descriptor above 2, got {}", PASSPHRASE_FD_ENV, fd));
+    }
+    // The caller hands this descriptor to us; nothing else in the process uses it
+    let mut file = unsafe { std::fs::File::from_raw_fd(fd) };
+    let mut passphrase = Zeroizing::new(String::new());
+    file.read_to_string(&mut passphrase)?;
+    let trimmed = passphrase.trim_end_matches(['\r', '\n']).len();
+    passphrase.truncate(trimmed);
+    Ok(passphrase)
+}
+
+#[cfg(not(unix))]
+fn read_passphrase_fd(_fd: i32) -> Result<Zeroizing<String>> {
+    Err(anyhow!("{} is only supported on unix", PASSPHRASE_FD_ENV))
+}
+
+/// Loads keypairs from plain JSON or keystore files, asking for the passphrase at most once
+#[derive(Default)]
+pub struct Unlocker {
+    passphrase: Option<Zeroizing<String>>,
+}
+
+impl Unlocker {
+    pub fn load(&mut self, path: &str) -> Result<Keypair> {
+        let contents = Zeroizing::new(std::fs::read_to_string(path)?);
+        // Plain Solana CLI keypairs are a JSON array, keystores a JSON object
+        if !contents.trim_start().starts_with('{') {
+            tracing::warn!("⚠️ {} is an unencrypted keypair; import it with `snipe keystore import json`", path);
+            return read_keypair(&mut contents.as_bytes()).map_err(|e| anyhow!("bad keypair file {}: {}", path, e));
+        }
+        let store: Keystore = serde_json::from_str(&contents)?;
+        if self.passphrase.is_none() {
+            self.passphrase = Some(read_passphrase("Keystore passphrase: ")?);
+        }
+        store.decrypt(self.passphrase.as_ref().unwrap())
+    }
+}
+
+pub fn keypair_from_base58(secret: &str) -> Result<Keypair> {
+    let bytes = Zeroizing::new(solana_sdk::bs58::decode(secret.trim()).into_vec()?);
+    Keypair::try_from(bytes.as_slice()).map_err(|e| anyhow!("Bad base58 secret key: {}", e))
+}
+
+pub fn keypair_from_mnemonic(phrase: &str, bip39_passphrase: &str, path: &str) -> Result<Keypair> {
+    let mnemonic = bip39::Mnemonic::parse(phrase.trim()).map_err(|e| anyhow!("Bad mnemonic: {}", e))?;
+    let seed = Zeroizing::new(mnemonic.to_seed(bip39_passphrase));
+    let path = DerivationPath::from_absolute_path_str(path).map_err(|e| anyhow!("Bad derivation path {}: {}", path, e))?;
+    keypair_from_seed_and_derivation_path(&seed[..], Some(path)).map_err(|e| anyhow!("Derivation failed: {}", e))
+}
+
+fn new_passphrase() -> Result<Zeroizing<String>> {
+    let passphrase = if std::env::var(PASSPHRASE_ENV).is_ok() || std::env::var(PASSPHRASE_FD_ENV).is_ok() {
+        read_passphrase("")?
+    } else {
+        let passphrase = Zeroizing::new(rpassword::prompt_password("New keystore passphrase: ")?);
+        let confirm = Zeroizing::new(rpassword::prompt_password("Repeat passphrase: ")?);
+        if passphrase != confirm {
+            return Err(anyhow!("Passphrases do not match"));
+        }
+        passphrase
+    };
+    if passphrase.is_empty() {
+        return Err(anyhow!("Refusing to use an empty passphrase"));
+    }
+    Ok(passphrase)
+}
+
+const USAGE: &str = "usage:
+  snipe keystore import json <keypair.json> <out.keystore>
+  snipe keystore import base58 <out.keystore>
+  snipe keystore import mnemonic <out.keystore> [derivation path, default m/44'/501'/0'/0']
+  snipe keystore show <file.keystore>";
+
+/// `snipe keystore ...`; secrets are always prompted for, never taken from argv
+pub fn run_cli(args: &[String]) -> Result<()> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let (keypair, out) = match args.as_slice() {
+        ["show", file] => {
+            let store = Keystore::read(Path::new(file))?;
+            tracing::info!("{} (v{}, argon2id m={} t={} p={})", store.pubkey, store.version, store.kdf.m_cost, store.kdf.t_cost, store.kdf.p_cost);
+            return Ok(());
+        }
+        ["import", "json", input, out] => {
+            (read_keypair_file(input).map_err(|e| anyhow!("bad keypair file {}: {}", input, e))?, *out)
+        }
+        ["import", "base58", out] => {
+            let secret = Zeroizing::new(rpassword::prompt_password("Base58 secret key: ")?);
+            (keypair_from_base58(&secret)?, *out)
+        }
+        ["import", "mnemonic", out, rest @ ..] if rest.len() <= 1 => {
+            let path = rest.first().copied().unwrap_or(DEFAULT_DERIVATION_PATH);
+            let phrase = Zeroizing::new(rpassword::prompt_password("Mnemonic: ")?);
+            let bip39_passphrase = Zeroizing::new(rpassword::prompt_password("BIP39 passphrase (empty for none): ")?);
+            (keypair_from_mnemonic(&phrase, &bip39_passphrase, path)?, *out)
+        }
+        _ => return Err(anyhow!("{}", USAGE)),
+    };
+
+    let passphrase = new_passphrase()?;
+    Keystore::encrypt(&keypair, &passphrase, KdfParams::default())?.write(Path::new(out))?;
+    tracing::info!("🔐 Wrote keystore for {} to {}", keypair.pubkey(), out);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cheap parameters so tests stay fast
+    const TEST_KDF: KdfParams = KdfParams { m_cost: 1024, t_cost: 1, p_cost: 1 };
+
+    #[test]
+    fn test_keystore_round_trip() {
+        let keypair = Keypair::new();
+        let store = Keystore::encrypt(&keypair, "hunter2", TEST_KDF).unwrap();
+        assert_eq!(store.pubkey, keypair.pubkey().to_string());
+        assert_eq!(store.decrypt("hunter2").unwrap().to_bytes(), keypair.to_bytes());
+        assert!(store.decrypt("hunter3").is_err());
+
+        // Swapping the advertised public key breaks authentication
+        let mut swapped = store.clone();
+        swapped.pubkey = Keypair::new().pubkey().to_string();
+        assert!(swapped.decrypt("hunter2").is_err());
+    }
+
+    #[test]
+    fn test_unlocker_reads_both_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        let plain = dir.path().join("id.json");
+        let sealed = dir.path().join("id.keystore");
+        let keypair = Keypair::new();
+        solana_sdk::signer::keypair::write_keypair_file(&keypair, &plain).unwrap();
+        Keystore::encrypt(&keypair, "pw", TEST_KDF).unwrap().write(&sealed).unwrap();
+        assert!(Keystore::encrypt(&keypair, "pw", TEST_KDF).unwrap().write(&sealed).is_err());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&sealed).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        let mut unlocker = Unlocker { passphrase: Some(Zeroizing::new("pw".to_string())) };
+        assert_eq!(unlocker.load(plain.to_str().unwrap()).unwrap().pubkey(), keypair.pubkey());
+        assert_eq!(unlocker.load(sealed.to_str().unwrap()).unwrap().pubkey(), keypair.pubkey());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_passphrase_fd_refuses_std_streams() {
+        for fd in 0..=2 {
+            assert!(read_passphrase_fd(fd).is_err());
+        }
+    }
+
+    #[test]
+    fn test_import_sources() {
+        let keypair = Keypair::new();
+        assert_eq!(keypair_from_base58(&keypair.to_base58_string()).unwrap().pubkey(), keypair.pubkey());
+        assert!(keypair_from_base58("not-base58!").is_err());
+
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let first = keypair_from_mnemonic(phrase, "", DEFAULT_DERIVATION_PATH).unwrap();
+        assert_eq!(first.pubkey(), keypair_from_mnemonic(phrase, "", DEFAULT_DERIVATION_PATH).unwrap().pubkey());
+        assert_ne!(first.pubkey(), keypair_from_mnemonic(phrase, "", "m/44'/501'/1'/0'").unwrap().pubkey());
+        assert_ne!(first.pubkey(), keypair_from_mnemonic(phrase, "extra", DEFAULT_DERIVATION_PATH).unwrap().pubkey());
+        assert!(keypair_from_mnemonic("abandon abandon", "", DEFAULT_DERIVATION_PATH).is_err());
+    }
+}
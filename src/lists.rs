@@ -0,0 +1,262 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Blocked and trusted mints/creators (`[lists]` table in config.toml)
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ListsConfig {
+    /// JSON file holding the lists; edits to it are picked up on the next signal
+    pub path: String,
+    /// Tokens from trusted creators skip the holder risk checks
+    pub trusted_skip_risk: bool,
+    /// Only buy tokens whose creator is trusted
+    pub require_trusted: bool,
+}
+
+impl Default for ListsConfig {
+    fn default() -> Self {
+        Self { path: "lists.json".to_string(), trusted_skip_risk: true, require_trusted: false }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListKind {
+    BlockedMint,
+    BlockedCreator,
+    TrustedCreator,
+}
+
+impl fmt::Display for ListKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListKind::BlockedMint => write!(f, "blocked mints"),
+            ListKind::BlockedCreator => write!(f, "blocked creators"),
+            ListKind::TrustedCreator => write!(f, "trusted creators"),
+        }
+    }
+}
+
+/// One listed address with who listed it and why
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ListEntry {
+    pub address: String,
+    #[serde(default)]
+    pub added_by: String,
+    #[serde(default)]
+    pub reason: String,
+    /// Unix seconds
+    #[serde(default)]
+    pub added_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct ListData {
+    pub blocked_mints: Vec<ListEntry>,
+    pub blocked_creators: Vec<ListEntry>,
+    pub trusted_creators: Vec<ListEntry>,
+}
+
+impl ListData {
+    fn list(&self, kind: ListKind) -> &Vec<ListEntry> {
+        match kind {
+            ListKind::BlockedMint => &self.blocked_mints,
+            ListKind::BlockedCreator => &self.blocked_creators,
+            ListKind::TrustedCreator => &self.trusted_creators,
+        }
+    }
+
+    fn list_mut(&mut self, kind: ListKind) -> &mut Vec<ListEntry> {
+        match kind {
+            ListKind::BlockedMint => &mut self.blocked_mints,
+            ListKind::BlockedCreator => &mut self.blocked_creators,
+            ListKind::TrustedCreator => &mut self.trusted_creators,
+        }
+    }
+
+    pub fn find(&self, kind: ListKind, address: &Pubkey) -> Option<&ListEntry> {
+        let address = address.to_string();
+        self.list(kind).iter().find(|e| e.address == address)
+    }
+}
+
+/// What the lists say about a signal
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Blocked(String),
+    Trusted,
+    Neutral,
+}
+
+/// Decide on a mint and its (possibly unknown) creator
+pub fn verdict(config: &ListsConfig, data: &ListData, mint: &Pubkey, creator: Option<&Pubkey>) -> Verdict {
+    let describe = |what: &str, e: &ListEntry| {
+        if e.reason.is_empty() {
+            format!("{} {} is blocked (by {})", what, e.address, e.added_by)
+        } else {
+            format!("{} {} is blocked (by {}: {})", what, e.address, e.added_by, e.reason)
+        }
+    };
+    if let Some(entry) = data.find(ListKind::BlockedMint, mint) {
+        return Verdict::Blocked(describe("mint", entry));
+    }
+    if let Some(creator) = creator {
+        if let Some(entry) = data.find(ListKind::BlockedCreator, creator) {
+            return Verdict::Blocked(describe("creator", entry));
+        }
+        if data.find(ListKind::TrustedCreator, creator).is_some() {
+            return Verdict::Trusted;
+        }
+    }
+    if config.require_trusted {
+        return Verdict::Blocked("creator is not trusted".to_string());
+    }
+    Verdict::Neutral
+}
+
+/// The lists, backed by a JSON file that is reloaded when it changes on disk
+pub struct Lists {
+    config: ListsConfig,
+    state: Mutex<(ListData, Option<SystemTime>)>,
+}
+
+impl Lists {
+    pub fn load(config: ListsConfig) -> Result<Self> {
+        let lists = Self { config, state: Mutex::new((ListData::default(), None)) };
+        lists.reload_if_changed()?;
+        Ok(lists)
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        std::fs::metadata(&self.config.path).and_then(|m| m.modified()).ok()
+    }
+
+    /// Pick up hand edits to the file
+    fn reload_if_changed(&self) -> Result<()> {
+        let modified = self.modified();
+        let mut state = self.state.lock().unwrap();
+        if modified.is_none() || modified == state.1 {
+            return Ok(());
+        }
+        let contents = std::fs::read_to_string(&self.config.path)?;
+        let data: ListData = serde_json::from_str(&contents)
+            .map_err(|e| anyhow!("Bad lists file {}: {}", self.config.path, e))?;
+        *state = (data, modified);
+        Ok(())
+    }
+
+    fn save(&self, data: &ListData) -> Result<()> {
+        let tmp = format!("{}.tmp", self.config.path);
+        std::fs::write(&tmp, serde_json::to_string_pretty(data)?)?;
+        std::fs::rename(&tmp, &self.config.path)?;
+        Ok(())
+    }
+
+    pub fn snapshot(&self) -> ListData {
+        if let Err(e) = self.reload_if_changed() {
+            tracing::warn!("{}", e);
+        }
+        self.state.lock().unwrap().0.clone()
+    }
+
+    pub fn verdict(&self, mint: &Pubkey, creator: Option<&Pubkey>) -> Verdict {
+        verdict(&self.config, &self.snapshot(), mint, creator)
+    }
+
+    pub fn config(&self) -> &ListsConfig {
+        &self.config
+    }
+
+    /// Whether a verdict depends on the creator, so it's worth looking up
+    pub fn needs_creator(&self) -> bool {
+        let data = self.snapshot();
+        self.config.require_trusted || !data.blocked_creators.is_empty() || !data.trusted_creators.is_empty()
+    }
+
+    /// Whether some creator is blocked, so a buy can't go ahead without knowing the creator
+    pub fn blocks_creators(&self) -> bool {
+        !self.snapshot().blocked_creators.is_empty()
+    }
+
+    /// Add or update an entry; returns false when it was already listed
+    pub fn add(&self, kind: ListKind, address: &Pubkey, added_by: &str, reason: &str) -> Result<bool> {
+        self.reload_if_changed()?;
+        let mut state = self.state.lock().unwrap();
+        let mut data = state.0.clone();
+        let existed = data.find(kind, address).is_some();
+        let address = address.to_string();
+        data.list_mut(kind).retain(|e| e.address != address);
+        let added_at = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_secs();
+        data.list_mut(kind).push(ListEntry {
+            address,
+            added_by: added_by.to_string(),
+            reason: reason.to_string(),
+            added_at,
+        });
+        self.save(&data)?;
+        *state = (data, self.modified());
+        Ok(!existed)
+    }
+
+    /// Returns false when the address wasn't listed
+    pub fn remove(&self, kind: ListKind, address: &Pubkey) -> Result<bool> {
+        self.reload_if_changed()?;
+        let mut state = self.state.lock().unwrap();
+        if state.0.find(kind, address).is_none() {
+            return Ok(false);
+        }
+        let mut data = state.0.clone();
+        let address = address.to_string();
+        data.list_mut(kind).retain(|e| e.address != address);
+        self.save(&data)?;
+        *state = (data, self.modified());
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verdict() {
+        let (mint, bad, good) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let entry = |address: &Pubkey| ListEntry { address: address.to_string(), added_by: "ops".into(), reason: "rug".into(), added_at: 0 };
+        let data = ListData {
+            blocked_mints: vec![entry(&mint)],
+            blocked_creators: vec![entry(&bad)],
+            trusted_creators: vec![entry(&good)],
+        };
+        let mut config = ListsConfig::default();
+        let other = Pubkey::new_unique();
+        assert!(matches!(verdict(&config, &data, &mint, Some(&good)), Verdict::Blocked(reason) if reason.contains("rug")));
+        assert!(matches!(verdict(&config, &data, &other, Some(&bad)), Verdict::Blocked(_)));
+        assert_eq!(verdict(&config, &data, &other, Some(&good)), Verdict::Trusted);
+        assert_eq!(verdict(&config, &data, &other, None), Verdict::Neutral);
+        config.require_trusted = true;
+        assert!(matches!(verdict(&config, &data, &other, None), Verdict::Blocked(_)));
+    }
+
+    #[test]
+    fn test_add_remove_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lists.json").to_str().unwrap().to_string();
+        let config = ListsConfig { path: path.clone(), ..Default::default() };
+        let lists = Lists::load(config.clone()).unwrap();
+        let creator = Pubkey::new_unique();
+        assert!(lists.add(ListKind::BlockedCreator, &creator, "alice", "serial rugger").unwrap());
+        assert!(!lists.add(ListKind::BlockedCreator, &creator, "bob", "again").unwrap());
+
+        let reloaded = Lists::load(config).unwrap().snapshot();
+        let entry = reloaded.find(ListKind::BlockedCreator, &creator).unwrap();
+        assert_eq!((entry.added_by.as_str(), entry.reason.as_str()), ("bob", "again"));
+        assert_eq!(reloaded.blocked_creators.len(), 1);
+
+        assert!(lists.remove(ListKind::BlockedCreator, &creator).unwrap());
+        assert!(!lists.remove(ListKind::BlockedCreator, &creator).unwrap());
+    }
+}
@@ -1,9 +1,8 @@
 use anyhow::{anyhow, Result};
 use serde::Deserialize;
-use solana_sdk::{signature::read_keypair_file, signer::keypair::Keypair};
-use solana_sdk::signer::Signer;
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tracing::info;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -17,6 +16,25 @@ mod swap;
 // mod grpc_listener;
 mod profit_db;
 mod telegram_bot;
+mod token;
+mod sweeper;
+mod paper;
+mod sizing;
+mod guards;
+mod sellability;
+mod risk;
+mod wallets;
+mod keystore;
+mod fills;
+mod nonce;
+mod coordinator;
+mod governor;
+mod lists;
+mod retry;
+mod exit;
+mod sell_all;
+mod curve;
+mod price_feed;
 
 #[derive(Deserialize, Clone)]
 pub struct Config {
@@ -35,6 +53,92 @@ pub struct Config {
     pub take_profit_pct: u32,
     pub stop_loss_pct: u32,
     pub trail_up_50_sl: bool,
+    /// Close the token account after a full sell to reclaim its rent
+    #[serde(default = "default_true")]
+    pub close_empty_accounts: bool,
+    /// Percent of the position sold when take profit hits; the rest rides with SL at entry
+    #[serde(default = "default_take_profit_sell_pct")]
+    pub take_profit_sell_pct: u8,
+    #[serde(default)]
+    pub jupiter: swap::JupiterConfig,
+    /// `live` trades on-chain, `paper` only simulates fills
+    #[serde(default)]
+    pub mode: paper::TradeMode,
+    #[serde(default)]
+    pub paper: paper::PaperConfig,
+    #[serde(default)]
+    pub sizing: sizing::SizingConfig,
+    #[serde(default)]
+    pub guards: guards::QuoteGuards,
+    #[serde(default)]
+    pub sellability: sellability::SellabilityConfig,
+    #[serde(default)]
+    pub risk: risk::RiskConfig,
+    #[serde(default)]
+    pub wallets: wallets::WalletConfig,
+    #[serde(default)]
+    pub nonce: nonce::NonceConfig,
+    #[serde(default)]
+    pub coordinator: coordinator::CoordinatorConfig,
+    #[serde(default)]
+    pub governor: governor::GovernorConfig,
+    #[serde(default)]
+    pub lists: lists::ListsConfig,
+    #[serde(default)]
+    pub retry: retry::RetryConfig,
+    #[serde(default)]
+    pub exit: exit::ExitConfig,
+    #[serde(default)]
+    pub sell_all: sell_all::SellAllConfig,
+    #[serde(default)]
+    pub price_feed: price_feed::PriceFeedConfig,
+    #[serde(default)]
+    pub trailing: strategy::TrailingConfig,
+}
+
+/// Shared trading state handed to the signal listener, Telegram and the buy path
+#[derive(Clone)]
+pub struct Services {
+    pub wallets: Arc<wallets::WalletPool>,
+    pub coordinator: Arc<coordinator::TradeCoordinator>,
+    pub governor: Arc<governor::RiskGovernor>,
+    pub lists: Arc<lists::Lists>,
+}
+
+impl Config {
+    pub fn is_paper(&self) -> bool {
+        self.mode == paper::TradeMode::Paper
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_take_profit_sell_pct() -> u8 {
+    100
+}
+
+/// Minimal config used by unit tests
+#[cfg(test)]
+pub fn test_config() -> Config {
+    toml::from_str(r#"
+        rpc_http = "http://127.0.0.1:8899"
+        grpc_addr = "http://127.0.0.1:10000"
+        grpc_x_token = ""
+        tg_token = ""
+        tg_chat = ""
+        tg_authorized_users = []
+        discord_webhook = ""
+        discord_token = ""
+        discord_channel_id = []
+        amount_sol = 0.1
+        slippage_bps = 500
+        priority_fee_microlamports = 100000
+        take_profit_pct = 400
+        stop_loss_pct = 65
+        trail_up_50_sl = true
+    "#).expect("test config should parse")
 }
 
 /// Get SOL balance for a given wallet address
@@ -48,8 +152,20 @@ pub async fn get_sol_balance(rpc_url: &str, wallet: &Pubkey) -> Result<f64> {
 #[tokio::main]
 async fn main() -> Result<()> {
     tracing_subscriber::fmt::init();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    
+    // Keystore management doesn't need a config
+    if args.first().map(String::as_str) == Some("keystore") {
+        return keystore::run_cli(&args[1..]);
+    }
+    
     let cfg: Config = toml::from_str(&std::fs::read_to_string("config.toml")?)?;
     
+    // One-shot maintenance commands: `snipe <command>`
+    if let Some(command) = args.first() {
+        return run_command(command, &cfg).await;
+    }
+    
     // Initialize profit database
     let profit_db = match profit_db::ProfitDatabase::new("profit_tracking.db") {
         Ok(db) => {
@@ -62,61 +178,94 @@ async fn main() -> Result<()> {
         }
     };
     
+    crate::notifier::log("Test notification on startup".to_string()).await;
+    let wallets = Arc::new(wallets::WalletPool::load(&cfg)?);
+    let governor = Arc::new(governor::RiskGovernor::new(cfg.governor.clone(), cfg.is_paper(), profit_db.clone()));
+    if let Some(halt) = governor.status() {
+        crate::notifier::log(format!("⏸️ New buys are {} from an earlier run; send /resume to lift it", halt)).await;
+    }
+    let services = Services {
+        wallets: wallets.clone(),
+        coordinator: coordinator::TradeCoordinator::new(cfg.coordinator.clone()),
+        governor: governor.clone(),
+        lists: Arc::new(lists::Lists::load(cfg.lists.clone())?),
+    };
+    
+    // Pick up positions a previous run left open before taking new signals
+    match buy::resume_positions(&cfg, &services, &profit_db).await {
+        Ok(0) => {}
+        Ok(resumed) => crate::notifier::log(format!("♻️ Resumed management of {} open position(s)", resumed)).await,
+        Err(e) => tracing::error!("Failed to resume open positions: {}", e),
+    }
+    
     // Initialize Telegram bot controller
     let telegram_controller = telegram_bot::TelegramController::new(
         cfg.tg_token.clone(),
         profit_db.clone(),
         cfg.tg_authorized_users.clone(),
         Some(cfg.tg_chat.clone()),
+        cfg.clone(),
+        services.clone(),
     );
     
-    crate::notifier::log("Test notification on startup".to_string()).await;
-    let payer = Arc::new(read_keypair_file("keys/id.json")
-        .map_err(|e| anyhow!("bad keypair file: {}", e))?);
-    
-    // Display wallet info and balance
-    info!("💰 Wallet Address: {}", payer.pubkey());
-    info!("💵 Trading with {} SOL per signal", cfg.amount_sol);
-    
-    // Check current SOL balance
-    let _balance = match get_sol_balance(&cfg.rpc_http, &payer.pubkey()).await {
-        Ok(balance) => {
-            info!("💰 Current SOL Balance: {:.4} SOL", balance);
-            let trades_possible = (balance / cfg.amount_sol).floor() as u32;
-            info!("📊 Trades possible with current balance: {}", trades_possible);
-            
-            if balance < cfg.amount_sol {
-                let msg = format!("⚠️ WARNING: Balance ({:.4} SOL) is less than trade amount ({} SOL)", balance, cfg.amount_sol);
-                tracing::warn!("{}", msg);
-                crate::notifier::log(msg).await;
-                
-                // Ask user if they want to continue anyway
-                tracing::warn!("💡 To fund your wallet, send SOL to: {}", payer.pubkey());
-                tracing::warn!("💡 You can also reduce the trade amount in config.toml (amount_sol = {})", cfg.amount_sol);
+    info!("💵 Trading with {} SOL per signal across {} wallet(s)", cfg.amount_sol, wallets.all().len());
+    if cfg.is_paper() {
+        info!("{} Paper trading mode: quotes are real, fills are simulated", paper::TAG);
+    }
+    
+    // Display wallet info and check every wallet's SOL balance
+    for wallet in wallets.all() {
+        info!("💰 Wallet {}: {}", wallet.name, wallet.pubkey());
+        match get_sol_balance(&cfg.rpc_http, &wallet.pubkey()).await {
+            Ok(balance) => {
+                info!("💰 Current SOL Balance of {}: {:.4} SOL", wallet.name, balance);
+                let trades_possible = (balance / cfg.amount_sol).floor() as u32;
+                info!("📊 Trades possible with current balance: {}", trades_possible);
                 
-                // For now, continue but warn heavily
-                if balance == 0.0 {
-                    tracing::error!("❌ CRITICAL: Wallet has 0 SOL - bot will not be able to trade!");
-                    tracing::error!("💡 Please fund your wallet with at least {} SOL to enable trading", cfg.amount_sol);
+                if balance < cfg.amount_sol {
+                    let msg = format!("⚠️ WARNING: {} balance ({:.4} SOL) is less than trade amount ({} SOL)", wallet.name, balance, cfg.amount_sol);
+                    tracing::warn!("{}", msg);
+                    crate::notifier::log(msg).await;
+                    
+                    tracing::warn!("💡 To fund your wallet, send SOL to: {}", wallet.pubkey());
+                    tracing::warn!("💡 You can also reduce the trade amount in config.toml (amount_sol = {})", cfg.amount_sol);
+                    
+                    // For now, continue but warn heavily
+                    if balance == 0.0 {
+                        tracing::error!("❌ CRITICAL: Wallet {} has 0 SOL - it will not be able to trade!", wallet.name);
+                        tracing::error!("💡 Please fund your wallet with at least {} SOL to enable trading", cfg.amount_sol);
+                    }
+                } else if balance < cfg.amount_sol * 3.0 {
+                    let msg = format!("💛 Low balance warning on {}: Only {:.4} SOL remaining ({}x trades possible)", wallet.name, balance, trades_possible);
+                    tracing::warn!("{}", msg);
+                    crate::notifier::log(msg).await;
                 }
-            } else if balance < cfg.amount_sol * 3.0 {
-                let msg = format!("💛 Low balance warning: Only {:.4} SOL remaining ({}x trades possible)", balance, trades_possible);
-                tracing::warn!("{}", msg);
-                crate::notifier::log(msg).await;
+                
+                crate::notifier::log(format!("💰 Bot started | Wallet {}: {} | Balance: {:.4} SOL", wallet.name, wallet.pubkey(), balance)).await;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to get SOL balance of {}: {}", wallet.name, e);
+                crate::notifier::log(format!("💰 Bot started with wallet {}: {} (balance check failed)", wallet.name, wallet.pubkey())).await;
             }
-            
-            crate::notifier::log(format!("💰 Bot started | Wallet: {} | Balance: {:.4} SOL", payer.pubkey(), balance)).await;
-            balance
         }
-        Err(e) => {
-            tracing::warn!("Failed to get SOL balance: {}", e);
-            crate::notifier::log(format!("💰 Bot started with wallet: {} (balance check failed)", payer.pubkey())).await;
-            0.0 // Assume 0 balance if we can't check
+    }
+    
+    // Durable nonce sells need their nonce accounts in place before the first exit
+    if cfg.nonce.enabled && !cfg.is_paper() {
+        if !cfg.jupiter.use_swap_instructions {
+            tracing::warn!("⚠️ nonce.enabled needs jupiter.use_swap_instructions; sells will use recent blockhashes");
+        } else {
+            let rpc = RpcClient::new(cfg.rpc_http.clone());
+            for wallet in wallets.all() {
+                if let Err(e) = nonce::ensure_accounts(&rpc, wallet, cfg.nonce.accounts).await {
+                    tracing::warn!("Could not set up nonce accounts for {}: {}", wallet.name, e);
+                }
+            }
         }
-    };
+    }
     
     let connected = Arc::new(AtomicBool::new(false));
-    let discord_task = tokio::spawn(discord_listener::run(cfg.clone(), payer.clone(), connected.clone()));
+    let discord_task = tokio::spawn(discord_listener::run(cfg.clone(), services, profit_db.clone(), connected.clone()));
     
     info!("Started Discord signal monitor");
     
@@ -138,16 +287,20 @@ async fn main() -> Result<()> {
         }
     });
 
-    // let grpc_task = tokio::spawn(grpc_listener::run(cfg.clone(), payer.pubkey()));
+    // let grpc_task = tokio::spawn(grpc_listener::run(cfg.clone(), wallets.primary().pubkey()));
 
     // Start periodic balance monitor (every 5 minutes)
-    let balance_monitor = tokio::spawn(periodic_balance_monitor(cfg.clone(), payer.clone()));
+    let balance_monitor = tokio::spawn(periodic_balance_monitor(cfg.clone(), wallets.clone()));
+
+    // Report loss limit trips even when no signals arrive
+    let governor_watch = tokio::spawn(async move { governor.watch().await });
 
     // Wait for either to finish or Ctrl+C
     tokio::select! {
         _ = discord_task => info!("Discord listener ended"),
         _ = telegram_task => info!("Telegram bot ended"),
         _ = balance_monitor => info!("Balance monitor ended"),
+        _ = governor_watch => info!("Risk governor ended"),
         _ = tokio::signal::ctrl_c() => info!("Received Ctrl+C, shutting down"),
     }
     Ok(())
@@ -155,43 +308,77 @@ async fn main() -> Result<()> {
 
 
 
-/// Periodic balance monitor - logs balance every 5 minutes
-async fn periodic_balance_monitor(cfg: Config, payer: Arc<Keypair>) -> Result<()> {
+/// Run a one-shot CLI command instead of the bot
+async fn run_command(command: &str, cfg: &Config) -> Result<()> {
+    let wallets = wallets::WalletPool::load(cfg)?;
+    let rpc = RpcClient::new(cfg.rpc_http.clone());
+    match command {
+        "sweep" => {
+            for wallet in wallets.all() {
+                let report = sweeper::sweep(&rpc, &wallet.keypair).await?;
+                info!("🧹 {}: closed {} empty token accounts ({} failed), reclaimed {:.6} SOL",
+                    wallet.name, report.closed, report.failed, report.reclaimed_sol());
+            }
+            Ok(())
+        }
+        "sell_all" => {
+            // The pause is stored in the database, so a running bot stops buying too
+            let db = profit_db::ProfitDatabase::new("profit_tracking.db")?;
+            governor::RiskGovernor::new(cfg.governor.clone(), cfg.is_paper(), db.clone()).pause();
+            tracing::warn!("🚨 Buys paused, selling every token in every wallet. Send /resume to buy again");
+            let report = sell_all::run(cfg, &wallets, &db).await;
+            let summary = report.summary();
+            info!("{}", summary);
+            crate::notifier::log(summary).await;
+            if report.failed() > 0 {
+                return Err(anyhow!("{} sell(s) failed", report.failed()));
+            }
+            Ok(())
+        }
+        _ => Err(anyhow!("Unknown command: {} (available: sweep, sell_all, keystore)", command)),
+    }
+}
+
+/// Periodic balance monitor - logs every wallet's balance every 5 minutes
+async fn periodic_balance_monitor(cfg: Config, wallets: Arc<wallets::WalletPool>) -> Result<()> {
     let mut interval = tokio::time::interval(std::time::Duration::from_secs(300)); // 5 minutes
-    let mut last_balance = 0.0;
+    let mut last_balances: HashMap<String, f64> = HashMap::new();
     
     loop {
         interval.tick().await;
         
-        match get_sol_balance(&cfg.rpc_http, &payer.pubkey()).await {
-            Ok(current_balance) => {
-                let balance_change = current_balance - last_balance;
-                
-                if last_balance > 0.0 {
-                    if balance_change.abs() > 0.001 { // Only log if change is significant
-                        let change_str = if balance_change > 0.0 {
-                            format!("+{:.4}", balance_change)
-                        } else {
-                            format!("{:.4}", balance_change)
-                        };
-                        
-                        tracing::info!("📊 Balance Update: {:.4} SOL ({})", current_balance, change_str);
-                        
-                        // Warn if balance is getting low
-                        let trades_possible = (current_balance / cfg.amount_sol).floor() as u32;
-                        if current_balance < cfg.amount_sol * 2.0 && current_balance > cfg.amount_sol {
-                            crate::notifier::log(format!("💛 Balance getting low: {:.4} SOL ({} trades left)", current_balance, trades_possible)).await;
+        for wallet in wallets.all() {
+            match get_sol_balance(&cfg.rpc_http, &wallet.pubkey()).await {
+                Ok(current_balance) => {
+                    let last_balance = last_balances.get(&wallet.name).copied().unwrap_or(0.0);
+                    let balance_change = current_balance - last_balance;
+                    
+                    if last_balance > 0.0 {
+                        if balance_change.abs() > 0.001 { // Only log if change is significant
+                            let change_str = if balance_change > 0.0 {
+                                format!("+{:.4}", balance_change)
+                            } else {
+                                format!("{:.4}", balance_change)
+                            };
+                            
+                            tracing::info!("📊 Balance Update ({}): {:.4} SOL ({})", wallet.name, current_balance, change_str);
+                            
+                            // Warn if balance is getting low
+                            let trades_possible = (current_balance / cfg.amount_sol).floor() as u32;
+                            if current_balance < cfg.amount_sol * 2.0 && current_balance > cfg.amount_sol {
+                                crate::notifier::log(format!("💛 Balance of {} getting low: {:.4} SOL ({} trades left)", wallet.name, current_balance, trades_possible)).await;
+                            }
                         }
+                    } else {
+                        tracing::debug!("📊 Periodic balance check ({}): {:.4} SOL", wallet.name, current_balance);
                     }
-                } else {
-                    tracing::debug!("📊 Periodic balance check: {:.4} SOL", current_balance);
+                    
+                    last_balances.insert(wallet.name.clone(), current_balance);
+                }
+                Err(e) => {
+                    tracing::debug!("Failed periodic balance check for {}: {}", wallet.name, e);
                 }
-                
-                last_balance = current_balance;
-            }
-            Err(e) => {
-                tracing::debug!("Failed periodic balance check: {}", e);
             }
         }
     }
-}
\ No newline at end of file
+}
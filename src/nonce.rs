@@ -0,0 +1,188 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSendTransactionConfig;
+use solana_sdk::hash::Hash;
+use solana_sdk::nonce::state::{State, Versions};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::signer::{keypair::Keypair, Signer};
+use solana_system_interface::instruction as system_instruction;
+use solana_sdk::transaction::{Transaction, VersionedTransaction};
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+use crate::wallets::Wallet;
+
+/// Durable nonce settings for sells (`[nonce]` table in config.toml)
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct NonceConfig {
+    /// Build sells on a durable nonce; needs `jupiter.use_swap_instructions`
+    pub enabled: bool,
+    /// Nonce accounts per wallet, i.e. how many sells can be in flight at once
+    pub accounts: usize,
+    pub rebroadcast_interval_ms: u64,
+    /// Give up rebroadcasting a signed sell after this long
+    pub max_rebroadcast_secs: u64,
+}
+
+impl Default for NonceConfig {
+    fn default() -> Self {
+        Self { enabled: false, accounts: 2, rebroadcast_interval_ms: 2_000, max_rebroadcast_secs: 120 }
+    }
+}
+
+/// Nonce accounts are derived from the wallet with these seeds, so nothing needs storing
+fn seed(index: usize) -> String {
+    format!("snipe-nonce-{}", index)
+}
+
+pub fn nonce_address(wallet: &Pubkey, index: usize) -> Result<Pubkey> {
+    Ok(Pubkey::create_with_seed(wallet, &seed(index), &solana_sdk::system_program::id())?)
+}
+
+/// Current nonce value of an initialized nonce account
+pub fn parse_nonce(data: &[u8]) -> Result<Hash> {
+    let versions: Versions = bincode::deserialize(data)?;
+    match versions.state() {
+        State::Initialized(data) => Ok(data.blockhash()),
+        State::Uninitialized => Err(anyhow!("Nonce account is not initialized")),
+    }
+}
+
+pub async fn fetch_nonce(rpc: &RpcClient, account: &Pubkey) -> Result<Hash> {
+    let account = rpc.get_account(account).await?;
+    parse_nonce(&account.data)
+}
+
+/// Create any of the wallet's nonce accounts that don't exist yet
+pub async fn ensure_accounts(rpc: &RpcClient, wallet: &Wallet, count: usize) -> Result<()> {
+    let payer: &Keypair = &wallet.keypair;
+    let rent = rpc.get_minimum_balance_for_rent_exemption(State::size()).await?;
+    for index in 0..count {
+        let address = nonce_address(&payer.pubkey(), index)?;
+        if rpc.get_account(&address).await.is_ok() {
+            continue;
+        }
+        let ixs = system_instruction::create_nonce_account_with_seed(
+            &payer.pubkey(), &address, &payer.pubkey(), &seed(index), &payer.pubkey(), rent,
+        );
+        let blockhash = rpc.get_latest_blockhash().await?;
+        let tx = Transaction::new_signed_with_payer(&ixs, Some(&payer.pubkey()), &[payer], blockhash);
+        let signature = rpc.send_and_confirm_transaction(&tx).await?;
+        info!("🔐 Created nonce account {} for wallet {}: {}", address, wallet.name, signature);
+    }
+    Ok(())
+}
+
+/// Nonce accounts currently backing an in-flight sell
+fn leased() -> &'static Mutex<HashSet<Pubkey>> {
+    static LEASED: OnceLock<Mutex<HashSet<Pubkey>>> = OnceLock::new();
+    LEASED.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+/// Exclusive use of one nonce account; two transactions on the same nonce can't both land
+pub struct NonceLease {
+    pub account: Pubkey,
+}
+
+impl NonceLease {
+    /// Take a free nonce account of `wallet`, if any
+    pub fn acquire(wallet: &Pubkey, count: usize) -> Option<Self> {
+        let mut leased = leased().lock().unwrap();
+        (0..count)
+            .filter_map(|index| nonce_address(wallet, index).ok())
+            .find(|account| leased.insert(*account))
+            .map(|account| Self { account })
+    }
+}
+
+impl Drop for NonceLease {
+    fn drop(&mut self) {
+        leased().lock().unwrap().remove(&self.account);
+    }
+}
+
+/// Broadcast a nonce transaction until it confirms, the nonce moves on without it,
+/// `still_wanted` says the position changed, or we run out of time
+pub async fn send_until_landed<F, Fut>(
+    cfg: &crate::Config,
+    rpc: &RpcClient,
+    tx: &VersionedTransaction,
+    nonce_account: &Pubkey,
+    still_wanted: F,
+) -> Result<Signature>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let signature = tx.signatures[0];
+    let nonce = *tx.message.recent_blockhash();
+    let send_config = RpcSendTransactionConfig { skip_preflight: true, max_retries: Some(0), ..Default::default() };
+    let interval = Duration::from_millis(cfg.nonce.rebroadcast_interval_ms.max(200));
+    let deadline = Instant::now() + Duration::from_secs(cfg.nonce.max_rebroadcast_secs);
+    let mut attempt: u32 = 0;
+    loop {
+        attempt += 1;
+        if let Err(e) = rpc.send_transaction_with_config(tx, send_config).await {
+            warn!("Broadcast {} of {} failed: {}", attempt, signature, e);
+        }
+        tokio::time::sleep(interval).await;
+
+        // Read the nonce before the status so a landed sell is never mistaken for a lost one
+        let current_nonce = fetch_nonce(rpc, nonce_account).await.ok();
+        let mut seen = false;
+        if let Ok(response) = rpc.get_signature_statuses(&[signature]).await {
+            if let Some(Some(status)) = response.value.first() {
+                if let Some(err) = &status.err {
+                    return Err(anyhow!("Transaction {} failed: {}", signature, err));
+                }
+                if status.satisfies_commitment(rpc.commitment()) {
+                    return Ok(signature);
+                }
+                seen = true;
+            }
+        }
+        if !seen && current_nonce.is_some_and(|current| current != nonce) {
+            return Err(anyhow!("Nonce {} advanced without {}", nonce_account, signature));
+        }
+        if !seen && !still_wanted().await {
+            return Err(anyhow!("Position changed, dropping {}", signature));
+        }
+        if Instant::now() >= deadline {
+            return Err(anyhow!("{} did not land after {} broadcasts", signature, attempt));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::nonce::state::{Data, DurableNonce};
+
+    #[test]
+    fn test_parse_nonce() {
+        let durable = DurableNonce::from_blockhash(&Hash::new_unique());
+        let state = Versions::new(State::Initialized(Data::new(Pubkey::new_unique(), durable, 5_000)));
+        let data = bincode::serialize(&state).unwrap();
+        assert_eq!(data.len(), State::size());
+        assert_eq!(parse_nonce(&data).unwrap(), *durable.as_hash());
+
+        let empty = bincode::serialize(&Versions::new(State::Uninitialized)).unwrap();
+        assert!(parse_nonce(&empty).is_err());
+    }
+
+    #[test]
+    fn test_leases_are_exclusive() {
+        let wallet = Pubkey::new_unique();
+        let first = NonceLease::acquire(&wallet, 2).unwrap();
+        let second = NonceLease::acquire(&wallet, 2).unwrap();
+        assert_ne!(first.account, second.account);
+        assert!(NonceLease::acquire(&wallet, 2).is_none());
+        drop(first);
+        assert_eq!(NonceLease::acquire(&wallet, 2).unwrap().account, nonce_address(&wallet, 0).unwrap());
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use crate::profit_db::ProfitDatabase;
+use crate::sell::{SellAmount, SellOutcome};
+use crate::Config;
+
+/// Whether trades hit the chain or only the paper ledger
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TradeMode {
+    #[default]
+    Live,
+    Paper,
+}
+
+/// Slippage model for simulated fills (`[paper]` table in config.toml)
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct PaperConfig {
+    /// Flat haircut applied to every quote
+    pub slippage_bps: u16,
+    /// Extra haircut as a multiple of the quote's `priceImpactPct`
+    pub impact_multiplier: f64,
+    /// Network + priority fee charged per simulated transaction
+    pub fee_lamports: u64,
+}
+
+impl Default for PaperConfig {
+    fn default() -> Self {
+        Self {
+            slippage_bps: 100,
+            impact_multiplier: 1.0,
+            fee_lamports: 105_000,
+        }
+    }
+}
+
+/// Prefix for notifications about paper trades
+pub const TAG: &str = "📝 [PAPER]";
+
+/// Output amount we assume we'd actually get for a quote
+pub fn simulated_fill(paper: &PaperConfig, quoted_out: u64, price_impact_pct: f64) -> u64 {
+    let impact_bps = (paper.impact_multiplier * price_impact_pct.max(0.0) * 10_000.0).round() as u64;
+    let haircut_bps = (paper.slippage_bps as u64 + impact_bps).min(10_000);
+    (quoted_out as u128 * (10_000 - haircut_bps) as u128 / 10_000) as u64
+}
+
+fn quoted_out(quote: &serde_json::Value) -> Result<(u64, f64)> {
+    if quote.get("routePlan").and_then(|v| v.as_array()).is_none_or(|arr| arr.is_empty()) {
+        return Err(anyhow!("No route found"));
+    }
+    let out = quote["outAmount"].as_str().and_then(|v| v.parse::<u64>().ok()).ok_or(anyhow!("Missing outAmount"))?;
+    let impact = quote["priceImpactPct"].as_str().and_then(|v| v.parse::<f64>().ok()).unwrap_or(0.0);
+    Ok((out, impact))
+}
+
+/// Simulate a buy: real quote, simulated fill recorded in the paper ledger.
+/// Returns the tokens received and the lamports spent including fees.
+pub async fn buy(wallet: &str, mint: Pubkey, lamports: u64, reference_out: Option<u64>, cfg: &Config, db: &ProfitDatabase) -> Result<(u64, u64)> {
+    let sol_mint = Pubkey::from_str("So11111111111111111111111111111111111111112")?;
+    let quote = crate::swap::guarded_quote(cfg, sol_mint, mint, lamports, reference_out).await?;
+    let (out, impact) = quoted_out(&quote)?;
+    let tokens = simulated_fill(&cfg.paper, out, impact);
+    let sol_spent = lamports + cfg.paper.fee_lamports;
+
+    db.record_paper_fill(wallet, &mint.to_string(), "buy", sol_spent, tokens)
+        .map_err(|e| anyhow!("Failed to record paper fill: {}", e))?;
+    tracing::info!("{} Simulated buy of {}: {} tokens for {} lamports (quoted {})", TAG, mint, tokens, sol_spent, out);
+    Ok((tokens, sol_spent))
+}
+
+/// Simulate a sell out of the paper ledger
+pub async fn sell(wallet: &str, mint: Pubkey, sell_amount: SellAmount, cfg: &Config, db: &ProfitDatabase) -> Result<SellOutcome> {
+    let balance = db.paper_token_balance(wallet, &mint.to_string())
+        .map_err(|e| anyhow!("Failed to read paper balance: {}", e))?;
+    let amount = sell_amount.resolve(balance);
+    if amount == 0 {
+        tracing::info!("{} No paper balance to sell for {}", TAG, mint);
+        return Ok(SellOutcome { tokens_sold: 0, transfer_fee: 0, expected_sol: None, sol_received: 0.0 });
+    }
+
+    // Transfer fees still apply on paper
+    let rpc = RpcClient::new(cfg.rpc_http.clone());
+    let mint_info = crate::token::fetch_mint(&rpc, &mint).await?;
+    let transfer_fee = if mint_info.transfer_fee.is_some() {
+        mint_info.transfer_fee(rpc.get_epoch_info().await?.epoch, amount)
+    } else {
+        0
+    };
+
+    let sol_mint = Pubkey::from_str("So11111111111111111111111111111111111111112")?;
+    let quote = crate::swap::guarded_quote(cfg, mint, sol_mint, amount - transfer_fee, None).await?;
+    let (out, impact) = quoted_out(&quote)?;
+    let lamports = simulated_fill(&cfg.paper, out, impact).saturating_sub(cfg.paper.fee_lamports);
+
+    db.record_paper_fill(wallet, &mint.to_string(), "sell", lamports, amount)
+        .map_err(|e| anyhow!("Failed to record paper fill: {}", e))?;
+    tracing::info!("{} Simulated sell of {} {}: {} lamports (quoted {})", TAG, amount, mint, lamports, out);
+    crate::notifier::log(format!("{} SOLD {} ({}) | {:.4} SOL", TAG, mint, sell_amount, lamports as f64 / 1e9)).await;
+
+    Ok(SellOutcome {
+        tokens_sold: amount,
+        transfer_fee,
+        expected_sol: Some(out as f64 / 1e9),
+        sol_received: lamports as f64 / 1e9,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simulated_fill() {
+        let paper = PaperConfig { slippage_bps: 100, impact_multiplier: 2.0, fee_lamports: 0 };
+        // 1% flat + 2 x 0.5% impact = 2% haircut
+        assert_eq!(simulated_fill(&paper, 1_000_000, 0.005), 980_000);
+        // Negative impact never improves the fill
+        assert_eq!(simulated_fill(&paper, 1_000_000, -0.1), 990_000);
+        // Haircut can't exceed the whole quote
+        assert_eq!(simulated_fill(&paper, 1_000_000, 5.0), 0);
+    }
+}
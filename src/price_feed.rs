@@ -0,0 +1,694 @@
+use anyhow::{anyhow, Result};
+use futures_util::future::BoxFuture;
+use futures_util::stream::{Stream, StreamExt, TryStreamExt};
+use serde::Deserialize;
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcAccountInfoConfig;
+use solana_client::rpc_response::Response;
+use solana_sdk::account::Account;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{timeout, Interval, MissedTickBehavior};
+use tracing::warn;
+use yellowstone_grpc_client::GeyserGrpcClient;
+use yellowstone_grpc_proto::prelude::subscribe_update::UpdateOneof;
+use yellowstone_grpc_proto::prelude::{SubscribeRequest, SubscribeRequestFilterAccounts, SubscribeUpdate};
+use crate::Config;
+use crate::curve::Launchpad;
+
+/// Where position prices come from (`[price_feed]` table in config.toml)
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct PriceFeedConfig {
+    /// Feeds in order of preference: `geyser`, `websocket`, `rpc` and `jupiter`
+    pub feeds: Vec<String>,
+    /// A feed that sends nothing for this long is failed over
+    pub silence_secs: u64,
+    /// Go back to the preferred feed after this long on a fallback
+    pub retry_preferred_secs: u64,
+    pub rpc_poll_interval_ms: u64,
+    pub quote_interval_ms: u64,
+    /// `accountSubscribe` endpoint; derived from `rpc_http` when unset
+    pub ws_url: Option<String>,
+}
+
+impl Default for PriceFeedConfig {
+    fn default() -> Self {
+        Self {
+            feeds: ["geyser", "websocket", "rpc", "jupiter"].map(String::from).to_vec(),
+            silence_secs: 30,
+            retry_preferred_secs: 120,
+            rpc_poll_interval_ms: 1_500,
+            quote_interval_ms: 2_000,
+            ws_url: None,
+        }
+    }
+}
+
+/// One price observation, in SOL per whole token
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tick {
+    pub price: f64,
+    /// The bonding curve is done and the token has graduated
+    pub complete: bool,
+}
+
+pub trait PriceFeed: Send {
+    fn name(&self) -> &str;
+
+    /// Wait for the next price; an error means the feed is broken
+    fn next(&mut self) -> BoxFuture<'_, Result<Tick>>;
+
+    /// Drop any connection and buffered updates; the next `next` starts afresh
+    fn reset(&mut self) {}
+}
+
+pub fn curve_tick(launchpad: Launchpad, data: &[u8]) -> Result<Tick> {
+    let curve = crate::curve::decode(launchpad, data)?;
+    Ok(Tick { price: curve.price(), complete: curve.complete })
+}
+
+/// SOL per whole token implied by buying `tokens_out` raw units for `lamports`
+pub fn probe_price(lamports: u64, tokens_out: u64, decimals: u8) -> f64 {
+    if tokens_out == 0 {
+        return 0.0;
+    }
+    (lamports as f64 / 1e9) / (tokens_out as f64 / 10f64.powi(decimals as i32))
+}
+
+/// Geometric mean of the buy and sell price of `tokens`, so the spread and fees on either side
+/// cancel out and the result lines up with a curve's spot price
+pub fn mid_price(buy_lamports: u64, sell_lamports: u64, tokens: u64, decimals: u8) -> f64 {
+    (probe_price(buy_lamports, tokens, decimals) * probe_price(sell_lamports, tokens, decimals)).sqrt()
+}
+
+fn out_amount(quote: &serde_json::Value) -> u64 {
+    quote["outAmount"].as_str().and_then(|v| v.parse::<u64>().ok()).unwrap_or(0)
+}
+
+/// Mid price from a small Jupiter buy quote and the sell quote of what it buys; routes through
+/// PumpSwap, Raydium or wherever the token trades
+async fn quote_price(cfg: &Config, mint: &Pubkey, decimals: u8) -> Result<f64> {
+    let sol_mint = Pubkey::from_str("So11111111111111111111111111111111111111112")?;
+    let buy = crate::swap::get_quote(cfg, sol_mint, *mint, crate::swap::PROBE_LAMPORTS, cfg.slippage_bps).await?;
+    let tokens = out_amount(&buy);
+    if tokens == 0 {
+        return Err(anyhow!("No quote for {}", mint));
+    }
+    let sell = crate::swap::get_quote(cfg, *mint, sol_mint, tokens, cfg.slippage_bps).await?;
+    let lamports = out_amount(&sell);
+    if lamports == 0 {
+        return Err(anyhow!("No sell quote for {}", mint));
+    }
+    Ok(mid_price(crate::swap::PROBE_LAMPORTS, lamports, tokens, decimals))
+}
+
+type Spawner = Box<dyn Fn(mpsc::Sender<Result<Tick>>) -> JoinHandle<()> + Send>;
+
+/// A streaming feed run as a background task, started on demand and restarted after it dies
+pub struct TaskFeed {
+    name: &'static str,
+    spawn: Spawner,
+    running: Option<(mpsc::Receiver<Result<Tick>>, JoinHandle<()>)>,
+}
+
+impl TaskFeed {
+    pub fn new(name: &'static str, spawn: impl Fn(mpsc::Sender<Result<Tick>>) -> JoinHandle<()> + Send + 'static) -> Self {
+        Self { name, spawn: Box::new(spawn), running: None }
+    }
+}
+
+impl PriceFeed for TaskFeed {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn next(&mut self) -> BoxFuture<'_, Result<Tick>> {
+        Box::pin(async move {
+            if self.running.is_none() {
+                let (tx, rx) = mpsc::channel(64);
+                self.running = Some((rx, (self.spawn)(tx)));
+            }
+            let (rx, _) = self.running.as_mut().expect("feed task was just started");
+            let received = rx.recv().await;
+            match received {
+                Some(Ok(tick)) => Ok(tick),
+                Some(Err(e)) => {
+                    self.reset();
+                    Err(e)
+                }
+                None => {
+                    self.reset();
+                    Err(anyhow!("{} feed ended", self.name))
+                }
+            }
+        })
+    }
+
+    fn reset(&mut self) {
+        if let Some((_, task)) = self.running.take() {
+            task.abort();
+        }
+    }
+}
+
+impl Drop for TaskFeed {
+    fn drop(&mut self) {
+        self.reset();
+    }
+}
+
+/// Send decoded curve updates until the receiver goes away
+async fn forward(tx: &mpsc::Sender<Result<Tick>>, launchpad: Launchpad, data: &[u8]) -> bool {
+    match curve_tick(launchpad, data) {
+        Ok(tick) => tx.send(Ok(tick)).await.is_ok(),
+        Err(e) => {
+            warn!("Could not decode {} curve update: {}", launchpad, e);
+            true
+        }
+    }
+}
+
+/// Forward every account update until the stream errors, ends or the receiver goes away
+async fn forward_all(
+    updates: impl Stream<Item = Result<Vec<u8>>>,
+    source: &str,
+    launchpad: Launchpad,
+    tx: &mpsc::Sender<Result<Tick>>,
+) -> Result<()> {
+    futures_util::pin_mut!(updates);
+    while let Some(data) = updates.try_next().await? {
+        if !forward(tx, launchpad, &data).await {
+            return Ok(());
+        }
+    }
+    Err(anyhow!("{} stream closed", source))
+}
+
+/// Account data in a Geyser update; other update kinds (pings) carry none
+fn geyser_account_data(update: SubscribeUpdate) -> Result<Option<Vec<u8>>> {
+    match update.update_oneof {
+        Some(UpdateOneof::Account(acc)) => Ok(Some(acc.account.ok_or(anyhow!("No account in update"))?.data)),
+        _ => Ok(None),
+    }
+}
+
+fn websocket_account_data(response: Response<UiAccount>) -> Result<Vec<u8>> {
+    Ok(response.value.decode::<Account>().ok_or(anyhow!("Undecodable account notification"))?.data)
+}
+
+async fn stream_geyser(addr: String, x_token: String, launchpad: Launchpad, account: Pubkey, tx: &mpsc::Sender<Result<Tick>>) -> Result<()> {
+    let mut client = GeyserGrpcClient::build_from_shared(addr)?
+        .x_token(Some(x_token))?
+        .connect().await?;
+    let req = SubscribeRequest {
+        accounts: HashMap::from([(
+            "bonding".to_string(),
+            SubscribeRequestFilterAccounts {
+                account: vec![account.to_string()],
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+    let stream = client.subscribe_once(req).await?;
+    let updates = stream.map_err(anyhow::Error::from).try_filter_map(|update| async move { geyser_account_data(update) });
+    forward_all(updates, "Geyser", launchpad, tx).await
+}
+
+/// Yellowstone gRPC account stream of the curve
+pub fn geyser(cfg: &Config, launchpad: Launchpad, account: Pubkey) -> TaskFeed {
+    let (addr, x_token) = (cfg.grpc_addr.clone(), cfg.grpc_x_token.clone());
+    TaskFeed::new("geyser", move |tx| {
+        let (addr, x_token) = (addr.clone(), x_token.clone());
+        tokio::spawn(async move {
+            if let Err(e) = stream_geyser(addr, x_token, launchpad, account, &tx).await {
+                let _ = tx.send(Err(e)).await;
+            }
+        })
+    })
+}
+
+async fn stream_websocket(url: String, launchpad: Launchpad, account: Pubkey, tx: &mpsc::Sender<Result<Tick>>) -> Result<()> {
+    let client = PubsubClient::new(&url).await?;
+    let config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        commitment: Some(CommitmentConfig::confirmed()),
+        ..Default::default()
+    };
+    let (stream, _unsubscribe) = client.account_subscribe(&account, Some(config)).await?;
+    forward_all(stream.map(websocket_account_data), "Websocket", launchpad, tx).await
+}
+
+fn ws_url(cfg: &Config) -> String {
+    match &cfg.price_feed.ws_url {
+        Some(url) => url.clone(),
+        None => cfg.rpc_http.replacen("https://", "wss://", 1).replacen("http://", "ws://", 1),
+    }
+}
+
+/// `accountSubscribe` notifications for the curve over the RPC websocket
+pub fn websocket(cfg: &Config, launchpad: Launchpad, account: Pubkey) -> TaskFeed {
+    let url = ws_url(cfg);
+    TaskFeed::new("websocket", move |tx| {
+        let url = url.clone();
+        tokio::spawn(async move {
+            if let Err(e) = stream_websocket(url, launchpad, account, &tx).await {
+                let _ = tx.send(Err(e)).await;
+            }
+        })
+    })
+}
+
+/// A polling schedule started on the first tick, so building a feed doesn't need a runtime
+struct PollInterval {
+    period: Duration,
+    interval: Option<Interval>,
+}
+
+impl PollInterval {
+    fn new(millis: u64) -> Self {
+        Self { period: Duration::from_millis(millis.max(100)), interval: None }
+    }
+
+    async fn tick(&mut self) {
+        let period = self.period;
+        let interval = self.interval.get_or_insert_with(|| {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval
+        });
+        interval.tick().await;
+    }
+}
+
+/// `getAccountInfo` on the curve at a fixed interval
+pub struct RpcPollFeed {
+    rpc: RpcClient,
+    launchpad: Launchpad,
+    account: Pubkey,
+    interval: PollInterval,
+}
+
+impl RpcPollFeed {
+    pub fn new(cfg: &Config, launchpad: Launchpad, account: Pubkey) -> Self {
+        Self {
+            rpc: RpcClient::new(cfg.rpc_http.clone()),
+            launchpad,
+            account,
+            interval: PollInterval::new(cfg.price_feed.rpc_poll_interval_ms),
+        }
+    }
+}
+
+impl PriceFeed for RpcPollFeed {
+    fn name(&self) -> &str {
+        "rpc"
+    }
+
+    fn next(&mut self) -> BoxFuture<'_, Result<Tick>> {
+        Box::pin(async move {
+            self.interval.tick().await;
+            let account = self.rpc.get_account(&self.account).await?;
+            curve_tick(self.launchpad, &account.data)
+        })
+    }
+}
+
+/// Jupiter mid quotes; the only feed that follows a token after it leaves its curve
+pub struct QuoteFeed {
+    cfg: Config,
+    mint: Pubkey,
+    decimals: u8,
+    interval: PollInterval,
+}
+
+impl QuoteFeed {
+    pub fn new(cfg: &Config, mint: Pubkey, decimals: u8) -> Self {
+        Self { cfg: cfg.clone(), mint, decimals, interval: PollInterval::new(cfg.price_feed.quote_interval_ms) }
+    }
+}
+
+impl PriceFeed for QuoteFeed {
+    fn name(&self) -> &str {
+        "jupiter"
+    }
+
+    fn next(&mut self) -> BoxFuture<'_, Result<Tick>> {
+        Box::pin(async move {
+            self.interval.tick().await;
+            let price = quote_price(&self.cfg, &self.mint, self.decimals).await?;
+            Ok(Tick { price, complete: false })
+        })
+    }
+}
+
+/// Runs the first feed and moves down the list when the current one errors or goes silent
+pub struct FailoverFeed {
+    feeds: Vec<Box<dyn PriceFeed>>,
+    active: usize,
+    silence: Duration,
+    retry_preferred: Duration,
+    switched_at: Instant,
+}
+
+impl FailoverFeed {
+    pub fn new(feeds: Vec<Box<dyn PriceFeed>>, silence: Duration, retry_preferred: Duration) -> Result<Self> {
+        if feeds.is_empty() {
+            return Err(anyhow!("No price feeds configured"));
+        }
+        Ok(Self { feeds, active: 0, silence, retry_preferred, switched_at: Instant::now() })
+    }
+
+    fn switch(&mut self, to: usize, reason: &str) {
+        self.feeds[self.active].reset();
+        warn!("📡 Price feed {} -> {}: {}", self.feeds[self.active].name(), self.feeds[to].name(), reason);
+        self.active = to;
+        self.switched_at = Instant::now();
+    }
+}
+
+impl PriceFeed for FailoverFeed {
+    fn name(&self) -> &str {
+        self.feeds[self.active].name()
+    }
+
+    /// Never fails: when every feed is down it keeps cycling through them
+    fn next(&mut self) -> BoxFuture<'_, Result<Tick>> {
+        Box::pin(async move {
+            if self.active != 0 && self.switched_at.elapsed() >= self.retry_preferred {
+                self.switch(0, "retrying the preferred feed");
+            }
+            let mut failures = 0;
+            loop {
+                let reason = match timeout(self.silence, self.feeds[self.active].next()).await {
+                    Ok(Ok(tick)) => return Ok(tick),
+                    Ok(Err(e)) => format!("failed: {}", e),
+                    Err(_) => format!("silent for {}s", self.silence.as_secs_f64()),
+                };
+                failures += 1;
+                if failures % self.feeds.len() == 0 {
+                    // Everything is down; don't spin
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+                self.switch((self.active + 1) % self.feeds.len(), &reason);
+            }
+        })
+    }
+
+    fn reset(&mut self) {
+        self.feeds[self.active].reset();
+    }
+}
+
+/// The configured feeds for a token still on its curve, with failover between them
+pub fn curve_feed(cfg: &Config, launchpad: Launchpad, account: Pubkey, mint: Pubkey, decimals: u8) -> Result<FailoverFeed> {
+    let mut feeds: Vec<Box<dyn PriceFeed>> = Vec::new();
+    for name in &cfg.price_feed.feeds {
+        match name.as_str() {
+            "geyser" => feeds.push(Box::new(geyser(cfg, launchpad, account))),
+            "websocket" => feeds.push(Box::new(websocket(cfg, launchpad, account))),
+            "rpc" => feeds.push(Box::new(RpcPollFeed::new(cfg, launchpad, account))),
+            "jupiter" => feeds.push(Box::new(QuoteFeed::new(cfg, mint, decimals))),
+            other => return Err(anyhow!("Unknown price feed: {}", other)),
+        }
+    }
+    FailoverFeed::new(
+        feeds,
+        Duration::from_secs(cfg.price_feed.silence_secs.max(1)),
+        Duration::from_secs(cfg.price_feed.retry_preferred_secs),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::VecDeque;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    enum Step {
+        Tick(f64),
+        Fail,
+        Hang,
+    }
+
+    /// Plays back a script; hangs once the script runs out
+    struct MockFeed {
+        name: &'static str,
+        steps: VecDeque<Step>,
+        resets: Arc<AtomicUsize>,
+    }
+
+    fn mock(name: &'static str, steps: Vec<Step>) -> (Box<dyn PriceFeed>, Arc<AtomicUsize>) {
+        let resets = Arc::new(AtomicUsize::new(0));
+        (Box::new(MockFeed { name, steps: steps.into(), resets: resets.clone() }), resets)
+    }
+
+    impl PriceFeed for MockFeed {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn next(&mut self) -> BoxFuture<'_, Result<Tick>> {
+            let step = self.steps.pop_front().unwrap_or(Step::Hang);
+            Box::pin(async move {
+                match step {
+                    Step::Tick(price) => Ok(Tick { price, complete: false }),
+                    Step::Fail => Err(anyhow!("boom")),
+                    Step::Hang => std::future::pending().await,
+                }
+            })
+        }
+
+        fn reset(&mut self) {
+            self.resets.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn failover(feeds: Vec<Box<dyn PriceFeed>>, retry_preferred: Duration) -> FailoverFeed {
+        FailoverFeed::new(feeds, Duration::from_millis(50), retry_preferred).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_failover_on_silence_and_error() {
+        let (preferred, preferred_resets) = mock("geyser", vec![Step::Tick(1.0), Step::Tick(2.0), Step::Hang]);
+        let (middle, _) = mock("websocket", vec![Step::Fail]);
+        let (backup, _) = mock("rpc", vec![Step::Tick(3.0)]);
+        let mut feed = failover(vec![preferred, middle, backup], Duration::from_secs(60));
+        assert_eq!(feed.next().await.unwrap().price, 1.0);
+        assert_eq!(feed.next().await.unwrap().price, 2.0);
+        // Geyser goes quiet, the websocket errors, polling takes over
+        assert_eq!(feed.next().await.unwrap().price, 3.0);
+        assert_eq!(feed.name(), "rpc");
+        assert_eq!(preferred_resets.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failover_returns_to_preferred() {
+        let (preferred, _) = mock("geyser", vec![Step::Fail, Step::Tick(7.0)]);
+        let (backup, _) = mock("jupiter", vec![Step::Tick(6.0), Step::Tick(6.5)]);
+        let mut feed = failover(vec![preferred, backup], Duration::ZERO);
+        assert_eq!(feed.next().await.unwrap().price, 6.0);
+        assert_eq!(feed.next().await.unwrap().price, 7.0);
+        assert_eq!(feed.name(), "geyser");
+        assert!(FailoverFeed::new(Vec::new(), Duration::from_secs(1), Duration::from_secs(1)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_task_feed_restarts_after_ending() {
+        let starts = Arc::new(AtomicUsize::new(0));
+        let counter = starts.clone();
+        let mut feed = TaskFeed::new("stream", move |tx| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            tokio::spawn(async move {
+                let _ = tx.send(Ok(Tick { price: 1.5, complete: false })).await;
+            })
+        });
+        assert_eq!(feed.next().await.unwrap().price, 1.5);
+        assert!(feed.next().await.is_err());
+        assert_eq!(feed.next().await.unwrap().price, 1.5);
+        assert_eq!(starts.load(Ordering::SeqCst), 2);
+    }
+
+    fn pump_curve(virtual_tokens: u64, virtual_sol: u64, complete: bool) -> Vec<u8> {
+        let mut data = vec![23, 183, 248, 55, 96, 216, 172, 96];
+        for value in [virtual_tokens, virtual_sol, 0, 0, 1_000_000_000_000_000] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.push(complete as u8);
+        data.resize(150, 0);
+        data
+    }
+
+    #[tokio::test]
+    async fn test_rpc_poll_feed() {
+        use base64::{engine::general_purpose, Engine};
+        let server = MockServer::start().await;
+        let data = general_purpose::STANDARD.encode(pump_curve(800_000_000_000_000, 40_000_000_000, true));
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "context": { "slot": 1 },
+                    "value": {
+                        "data": [data, "base64"],
+                        "executable": false,
+                        "lamports": 1_500_000,
+                        "owner": crate::curve::PUMP_FUN_PROGRAM,
+                        "rentEpoch": 0,
+                        "space": 150
+                    }
+                }
+            })))
+            .mount(&server)
+            .await;
+        let mut cfg = crate::test_config();
+        cfg.rpc_http = server.uri();
+        let mut feed = RpcPollFeed::new(&cfg, Launchpad::PumpFun, Pubkey::new_unique());
+        let tick = feed.next().await.unwrap();
+        assert!((tick.price - 5e-8).abs() < 1e-18);
+        assert!(tick.complete);
+    }
+
+    #[tokio::test]
+    async fn test_quote_feed() {
+        let server = MockServer::start().await;
+        let mint = Pubkey::new_unique();
+        // 0.001 SOL buys 20,000 tokens, which sell back for 0.00064 SOL
+        Mock::given(method("GET"))
+            .and(path("/quote"))
+            .and(query_param("outputMint", mint.to_string()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "outAmount": "20000000000", "routePlan": [{}] })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/quote"))
+            .and(query_param("inputMint", mint.to_string()))
+            .and(query_param("amount", "20000000000"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "outAmount": "640000", "routePlan": [{}] })))
+            .mount(&server)
+            .await;
+        let mut cfg = crate::test_config();
+        cfg.jupiter.base_url = server.uri();
+        let mut feed = QuoteFeed::new(&cfg, mint, 6);
+        let tick = feed.next().await.unwrap();
+        assert!((tick.price - 4e-8).abs() < 1e-18);
+        assert!(!tick.complete);
+        assert_eq!(feed.name(), "jupiter");
+    }
+
+    #[test]
+    fn test_mid_price() {
+        // Ask 5e-8 and bid 3.2e-8 SOL per token meet at 4e-8
+        assert!((mid_price(1_000_000, 640_000, 20_000_000_000, 6) - 4e-8).abs() < 1e-18);
+        assert!((mid_price(1_000_000, 1_000_000, 20_000_000_000, 6) - 5e-8).abs() < 1e-18);
+        assert_eq!(mid_price(1_000_000, 640_000, 0, 6), 0.0);
+    }
+
+    async fn collect_ticks(updates: impl Stream<Item = Result<Vec<u8>>>) -> (Vec<f64>, Result<()>) {
+        let (tx, mut rx) = mpsc::channel(16);
+        let result = forward_all(updates, "test", Launchpad::PumpFun, &tx).await;
+        drop(tx);
+        let mut prices = Vec::new();
+        while let Some(tick) = rx.recv().await {
+            prices.push(tick.unwrap().price);
+        }
+        (prices, result)
+    }
+
+    #[tokio::test]
+    async fn test_forward_all() {
+        let updates = vec![Ok(pump_curve(800_000_000_000_000, 40_000_000_000, false)), Ok(vec![1, 2, 3]), Ok(pump_curve(400_000_000_000_000, 40_000_000_000, false))];
+        let (prices, result) = collect_ticks(futures_util::stream::iter(updates)).await;
+        // The undecodable update is skipped; the stream ending is an error so the feed restarts
+        assert_eq!(prices.len(), 2);
+        assert!((prices[0] - 5e-8).abs() < 1e-18 && (prices[1] - 1e-7).abs() < 1e-18);
+        assert!(result.unwrap_err().to_string().contains("closed"));
+
+        let updates = vec![Ok(pump_curve(800_000_000_000_000, 40_000_000_000, false)), Err(anyhow!("reset by peer"))];
+        let (prices, result) = collect_ticks(futures_util::stream::iter(updates)).await;
+        assert_eq!(prices.len(), 1);
+        assert!(result.unwrap_err().to_string().contains("reset by peer"));
+
+        // A receiver that went away ends the stream cleanly
+        let (tx, rx) = mpsc::channel(1);
+        drop(rx);
+        let updates = futures_util::stream::iter(vec![Ok(pump_curve(800_000_000_000_000, 40_000_000_000, false))]);
+        assert!(forward_all(updates, "test", Launchpad::PumpFun, &tx).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_geyser_updates() {
+        use yellowstone_grpc_proto::prelude::{SubscribeUpdateAccount, SubscribeUpdateAccountInfo, SubscribeUpdatePing};
+        let account = |data: Option<Vec<u8>>| SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Account(SubscribeUpdateAccount {
+                account: data.map(|data| SubscribeUpdateAccountInfo { data, ..Default::default() }),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        let ping = SubscribeUpdate { update_oneof: Some(UpdateOneof::Ping(SubscribeUpdatePing {})), ..Default::default() };
+        assert_eq!(geyser_account_data(ping.clone()).unwrap(), None);
+        assert!(geyser_account_data(account(None)).is_err());
+
+        let updates = vec![Ok(ping), Ok(account(Some(pump_curve(800_000_000_000_000, 40_000_000_000, true))))];
+        let updates = futures_util::stream::iter(updates).try_filter_map(|update| async move { geyser_account_data(update) });
+        let (prices, result) = collect_ticks(updates).await;
+        assert_eq!(prices.len(), 1);
+        assert!((prices[0] - 5e-8).abs() < 1e-18);
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_websocket_updates() {
+        use base64::{engine::general_purpose, Engine};
+        let notification = |data: Vec<u8>| -> Response<UiAccount> {
+            serde_json::from_value(json!({
+                "context": { "slot": 1 },
+                "value": {
+                    "data": [general_purpose::STANDARD.encode(data), "base64"],
+                    "executable": false,
+                    "lamports": 1_500_000,
+                    "owner": crate::curve::PUMP_FUN_PROGRAM,
+                    "rentEpoch": 0,
+                    "space": 150
+                }
+            }))
+            .unwrap()
+        };
+        let updates = vec![notification(pump_curve(800_000_000_000_000, 40_000_000_000, false)), notification(pump_curve(800_000_000_000_000, 80_000_000_000, true))];
+        let (prices, result) = collect_ticks(futures_util::stream::iter(updates).map(websocket_account_data)).await;
+        assert_eq!(prices.len(), 2);
+        assert!((prices[0] - 5e-8).abs() < 1e-18 && (prices[1] - 1e-7).abs() < 1e-18);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_probe_price() {
+        // 0.001 SOL buys 20,000 six-decimal tokens: 5e-8 SOL each
+        assert!((probe_price(1_000_000, 20_000_000_000, 6) - 5e-8).abs() < 1e-18);
+        assert!((probe_price(1_000_000, 20_000_000_000_000, 9) - 5e-8).abs() < 1e-18);
+        assert_eq!(probe_price(1_000_000, 0, 6), 0.0);
+    }
+
+    #[test]
+    fn test_ws_url_and_feed_list() {
+        let mut cfg = crate::test_config();
+        cfg.rpc_http = "https://rpc.example.com/?key=1".to_string();
+        assert_eq!(ws_url(&cfg), "wss://rpc.example.com/?key=1");
+        cfg.price_feed.feeds = vec!["rpc".to_string(), "carrier-pigeon".to_string()];
+        assert!(curve_feed(&cfg, Launchpad::PumpFun, Pubkey::new_unique(), Pubkey::new_unique(), 6).is_err());
+    }
+}
@@ -1,7 +1,8 @@
-use rusqlite::{Connection, Result as SqliteResult};
+use rusqlite::{Connection, OptionalExtension, Result as SqliteResult};
 use tracing::info;
 use std::sync::Arc;
 use std::sync::Mutex;
+use crate::fills::Fill;
 
 pub struct ProfitDatabase {
     conn: Arc<Mutex<Connection>>,
@@ -35,6 +36,73 @@ impl ProfitDatabase {
             [],
         )?;
 
+        // Per-trade history; paper trades are tagged and kept out of the live totals
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS trades (
+                id INTEGER PRIMARY KEY,
+                mint TEXT NOT NULL,
+                pnl REAL NOT NULL,
+                paper INTEGER NOT NULL DEFAULT 0,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+
+        ensure_column(&conn, "trades", "source", "TEXT NOT NULL DEFAULT ''")?;
+        ensure_column(&conn, "trades", "cost_sol", "REAL NOT NULL DEFAULT 0.0")?;
+        ensure_column(&conn, "trades", "wallet", "TEXT NOT NULL DEFAULT 'main'")?;
+        // Partial exits of one position share its id so stats can count the position once
+        ensure_column(&conn, "trades", "position_id", "INTEGER")?;
+
+        // Simulated fills for paper trading
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS paper_fills (
+                id INTEGER PRIMARY KEY,
+                mint TEXT NOT NULL,
+                side TEXT NOT NULL,
+                sol_lamports INTEGER NOT NULL,
+                tokens INTEGER NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+        ensure_column(&conn, "paper_fills", "wallet", "TEXT NOT NULL DEFAULT 'main'")?;
+
+        // Positions under management, so they can be picked up again after a restart
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS positions (
+                id INTEGER PRIMARY KEY,
+                wallet TEXT NOT NULL,
+                mint TEXT NOT NULL,
+                source TEXT NOT NULL DEFAULT '',
+                paper INTEGER NOT NULL DEFAULT 0,
+                cost_sol REAL NOT NULL,
+                remaining_cost REAL NOT NULL,
+                fill_sol_spent INTEGER,
+                fill_fee INTEGER,
+                fill_rent INTEGER,
+                fill_tokens INTEGER,
+                entry_price REAL,
+                stop_price REAL,
+                hit_breakeven INTEGER NOT NULL DEFAULT 0,
+                took_partial_profit INTEGER NOT NULL DEFAULT 0,
+                status TEXT NOT NULL DEFAULT 'open',
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+        ensure_column(&conn, "positions", "peak_price", "REAL")?;
+
+        // Switches shared with other processes, such as a buy pause set from the CLI
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS flags (
+                name TEXT PRIMARY KEY,
+                value INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
         // Insert initial record if table is empty
         let count: i32 = conn.query_row("SELECT COUNT(*) FROM profit_tracking", [], |row| row.get(0))?;
         if count == 0 {
@@ -79,7 +147,6 @@ impl ProfitDatabase {
     }
 
     /// Add profit from a trade
-    #[allow(dead_code)]
     pub fn add_profit(&self, profit: f64) -> SqliteResult<()> {
         let stats = self.get_profit()?;
         let new_total_profit = stats.total_profit + profit;
@@ -113,6 +180,224 @@ impl ProfitDatabase {
         Ok(())
     }
 
+    /// Record a closed trade; live trades also update the running totals
+    pub fn record_trade(&self, trade: &TradeRecord) -> SqliteResult<()> {
+        {
+            let conn = self.conn.lock().unwrap();
+            conn.execute(
+                "INSERT INTO trades (wallet, mint, source, cost_sol, pnl, paper, position_id) VALUES (?, ?, ?, ?, ?, ?, ?)",
+                rusqlite::params![trade.wallet, trade.mint, trade.source, trade.cost_sol, trade.pnl, trade.paper, trade.position_id],
+            )?;
+        }
+        if !trade.paper {
+            self.add_profit(trade.pnl)?;
+        }
+        Ok(())
+    }
+
+    /// Returns (pnl / cost) of the most recent positions, live or paper only, partial exits combined
+    pub fn recent_returns(&self, paper: bool, limit: u32) -> SqliteResult<Vec<f64>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT SUM(pnl) / SUM(cost_sol) FROM trades WHERE paper = ? AND cost_sol > 0
+             GROUP BY COALESCE(position_id, -id) ORDER BY MAX(id) DESC LIMIT ?",
+        )?;
+        let returns = stmt.query_map(rusqlite::params![paper, limit], |row| row.get(0))?.collect();
+        returns
+    }
+
+    /// (positions, wins) recorded for a signal source, live or paper only
+    pub fn source_record(&self, source: &str, paper: bool) -> SqliteResult<(u32, u32)> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(CASE WHEN pnl > 0 THEN 1 ELSE 0 END), 0) FROM (
+                SELECT SUM(pnl) AS pnl FROM trades WHERE source = ? AND paper = ? GROUP BY COALESCE(position_id, -id)
+             )",
+            rusqlite::params![source, paper],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+    }
+
+    /// Record a simulated fill in the paper ledger
+    pub fn record_paper_fill(&self, wallet: &str, mint: &str, side: &str, sol_lamports: u64, tokens: u64) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO paper_fills (wallet, mint, side, sol_lamports, tokens) VALUES (?, ?, ?, ?, ?)",
+            rusqlite::params![wallet, mint, side, sol_lamports as i64, tokens as i64],
+        )?;
+        Ok(())
+    }
+
+    /// Tokens currently held on paper for a mint
+    pub fn paper_token_balance(&self, wallet: &str, mint: &str) -> SqliteResult<u64> {
+        let conn = self.conn.lock().unwrap();
+        let balance: i64 = conn.query_row(
+            "SELECT COALESCE(SUM(CASE WHEN side = 'buy' THEN tokens ELSE -tokens END), 0)
+             FROM paper_fills WHERE wallet = ? AND mint = ?",
+            [wallet, mint],
+            |row| row.get(0),
+        )?;
+        Ok(balance.max(0) as u64)
+    }
+
+    /// Mints with a positive paper balance in a wallet, with the balance
+    pub fn paper_holdings(&self, wallet: &str) -> SqliteResult<Vec<(String, u64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT mint, SUM(CASE WHEN side = 'buy' THEN tokens ELSE -tokens END) AS balance
+             FROM paper_fills WHERE wallet = ? GROUP BY mint HAVING balance > 0 ORDER BY mint",
+        )?;
+        let rows = stmt.query_map([wallet], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as u64)))?;
+        rows.collect()
+    }
+
+    /// Realized (pnl, trades) per wallet, paper trades excluded
+    pub fn wallet_pnl(&self) -> SqliteResult<Vec<(String, f64, u32)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT wallet, SUM(pnl), COUNT(*) FROM trades WHERE paper = 0 GROUP BY wallet ORDER BY wallet",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect()
+    }
+
+    /// (id, pnl) of closed trades from the current UTC day after `after_id`, oldest first
+    pub fn todays_trades(&self, paper: bool, after_id: i64) -> SqliteResult<Vec<(i64, f64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, pnl FROM trades WHERE paper = ? AND id > ? AND date(created_at) = date('now') ORDER BY id",
+        )?;
+        let rows = stmt.query_map(rusqlite::params![paper, after_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// (id, pnl) of the latest `limit` closed trades after `after_id` on any day, newest first
+    pub fn recent_trades(&self, paper: bool, after_id: i64, limit: u32) -> SqliteResult<Vec<(i64, f64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT id, pnl FROM trades WHERE paper = ? AND id > ? ORDER BY id DESC LIMIT ?")?;
+        let rows = stmt.query_map(rusqlite::params![paper, after_id, limit], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// Store a newly opened position, returning its id
+    pub fn insert_position(&self, position: &PositionRecord) -> SqliteResult<i64> {
+        let conn = self.conn.lock().unwrap();
+        let fill = position.fill;
+        conn.execute(
+            "INSERT INTO positions (wallet, mint, source, paper, cost_sol, remaining_cost, fill_sol_spent, fill_fee, fill_rent,
+                fill_tokens, entry_price, stop_price, peak_price, hit_breakeven, took_partial_profit, status)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            rusqlite::params![
+                position.wallet, position.mint, position.source, position.paper, position.cost_sol, position.remaining_cost,
+                fill.map(|f| f.sol_spent as i64), fill.map(|f| f.fee as i64), fill.map(|f| f.rent as i64), fill.map(|f| f.tokens as i64),
+                position.entry_price, position.stop_price, position.peak_price, position.hit_breakeven, position.took_partial_profit,
+                position.status,
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Save the exit state of a stored position; a position closed elsewhere stays closed
+    pub fn update_position(&self, position: &PositionRecord) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE positions SET remaining_cost = ?, entry_price = ?, stop_price = ?, peak_price = ?,
+                hit_breakeven = ?, took_partial_profit = ?, status = ?, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ? AND status = 'open'",
+            rusqlite::params![
+                position.remaining_cost, position.entry_price, position.stop_price, position.peak_price,
+                position.hit_breakeven, position.took_partial_profit, position.status, position.id,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_position_status(&self, id: i64, status: &str) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE positions SET status = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+            rusqlite::params![status, id],
+        )?;
+        Ok(())
+    }
+
+    pub fn position_status(&self, id: i64) -> SqliteResult<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT status FROM positions WHERE id = ?", [id], |row| row.get(0)).optional()
+    }
+
+    pub fn set_flag(&self, name: &str, value: bool) -> SqliteResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO flags (name, value) VALUES (?, ?) ON CONFLICT(name) DO UPDATE SET value = excluded.value",
+            rusqlite::params![name, value],
+        )?;
+        Ok(())
+    }
+
+    pub fn flag(&self, name: &str) -> SqliteResult<bool> {
+        let conn = self.conn.lock().unwrap();
+        let value = conn.query_row("SELECT value FROM flags WHERE name = ?", [name], |row| row.get(0)).optional()?;
+        Ok(value.unwrap_or(false))
+    }
+
+    /// Positions still open, oldest first
+    pub fn open_positions(&self, paper: bool) -> SqliteResult<Vec<PositionRecord>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, wallet, mint, source, paper, cost_sol, remaining_cost, fill_sol_spent, fill_fee, fill_rent, fill_tokens,
+                entry_price, stop_price, hit_breakeven, took_partial_profit, status, peak_price
+             FROM positions WHERE status = ? AND paper = ? ORDER BY id",
+        )?;
+        let rows = stmt.query_map(rusqlite::params![POSITION_OPEN, paper], |row| {
+            let fill = match (row.get::<_, Option<i64>>(7)?, row.get::<_, Option<i64>>(8)?, row.get::<_, Option<i64>>(9)?, row.get::<_, Option<i64>>(10)?) {
+                (Some(sol_spent), Some(fee), Some(rent), Some(tokens)) => Some(Fill {
+                    sol_spent: sol_spent as u64,
+                    fee: fee as u64,
+                    rent: rent as u64,
+                    tokens: tokens as u64,
+                }),
+                _ => None,
+            };
+            Ok(PositionRecord {
+                id: row.get(0)?,
+                wallet: row.get(1)?,
+                mint: row.get(2)?,
+                source: row.get(3)?,
+                paper: row.get(4)?,
+                cost_sol: row.get(5)?,
+                remaining_cost: row.get(6)?,
+                fill,
+                entry_price: row.get(11)?,
+                stop_price: row.get(12)?,
+                hit_breakeven: row.get(13)?,
+                took_partial_profit: row.get(14)?,
+                status: row.get(15)?,
+                peak_price: row.get(16)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Get paper trading results as a formatted string
+    pub fn get_paper_summary(&self) -> SqliteResult<String> {
+        let conn = self.conn.lock().unwrap();
+        let (total, trades, wins): (f64, i32, i32) = conn.query_row(
+            "SELECT COALESCE(SUM(pnl), 0.0), COUNT(*), COALESCE(SUM(CASE WHEN pnl > 0 THEN 1 ELSE 0 END), 0)
+             FROM trades WHERE paper = 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        let win_rate = if trades > 0 { wins as f64 / trades as f64 * 100.0 } else { 0.0 };
+        Ok(format!(
+            "📝 Paper Trading Summary:\n\
+             Total Profit: {:.4} SOL\n\
+             Total Trades: {}\n\
+             Win Rate: {:.1}%",
+            total, trades, win_rate
+        ))
+    }
+
     /// Reset all profit data to zero
     pub fn reset_profit(&self) -> SqliteResult<()> {
         let conn = self.conn.lock().unwrap();
@@ -165,6 +450,16 @@ impl ProfitDatabase {
     }
 }
 
+/// Add a column to an existing table if an older database lacks it
+fn ensure_column(conn: &Connection, table: &str, column: &str, decl: &str) -> SqliteResult<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt.query_map([], |row| row.get::<_, String>(1))?.collect::<SqliteResult<Vec<_>>>()?;
+    if !columns.iter().any(|name| name == column) {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl), [])?;
+    }
+    Ok(())
+}
+
 impl Clone for ProfitDatabase {
     fn clone(&self) -> Self {
         Self {
@@ -173,6 +468,45 @@ impl Clone for ProfitDatabase {
     }
 }
 
+pub const POSITION_OPEN: &str = "open";
+pub const POSITION_CLOSED: &str = "closed";
+
+/// A row of the `positions` table
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionRecord {
+    pub id: i64,
+    pub wallet: String,
+    pub mint: String,
+    pub source: String,
+    pub paper: bool,
+    pub cost_sol: f64,
+    /// Cost not yet closed out by partial exits
+    pub remaining_cost: f64,
+    pub fill: Option<Fill>,
+    /// Curve price the exit levels are measured from, once seen
+    pub entry_price: Option<f64>,
+    pub stop_price: Option<f64>,
+    /// Highest price seen since entry, the high-water mark the trailing stop follows
+    pub peak_price: Option<f64>,
+    pub hit_breakeven: bool,
+    pub took_partial_profit: bool,
+    /// `open` or `closed`
+    pub status: String,
+}
+
+/// A row of the `trades` table: a closed trade, or one exit of a position
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TradeRecord<'a> {
+    pub wallet: &'a str,
+    pub mint: &'a str,
+    pub source: &'a str,
+    pub cost_sol: f64,
+    pub pnl: f64,
+    pub paper: bool,
+    /// Position the exit closes out, so partial exits count as one trade
+    pub position_id: Option<i64>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ProfitStats {
     pub total_profit: f64,
@@ -248,4 +582,137 @@ mod tests {
         assert_eq!(stats.total_profit, 0.0);
         assert_eq!(stats.total_trades, 0);
     }
-} 
\ No newline at end of file
+
+    fn trade<'a>(wallet: &'a str, pnl: f64, paper: bool) -> TradeRecord<'a> {
+        TradeRecord { wallet, mint: "mint", source: "chan", cost_sol: 1.0, pnl, paper, position_id: None }
+    }
+
+    #[test]
+    fn test_paper_trades_stay_out_of_live_totals() {
+        let temp_dir = tempdir().unwrap();
+        let db = ProfitDatabase::new(temp_dir.path().join("paper.db").to_str().unwrap()).unwrap();
+        db.record_trade(&trade("main", 3.0, true)).unwrap();
+        db.record_trade(&trade("main", -0.5, true)).unwrap();
+        assert_eq!(db.get_profit().unwrap().total_trades, 0);
+        assert!(db.wallet_pnl().unwrap().is_empty());
+        let summary = db.get_paper_summary().unwrap();
+        assert!(summary.contains("Total Profit: 2.5000 SOL") && summary.contains("Total Trades: 2"));
+    }
+
+    #[test]
+    fn test_paper_ledger_balances() {
+        let temp_dir = tempdir().unwrap();
+        let db = ProfitDatabase::new(temp_dir.path().join("paper.db").to_str().unwrap()).unwrap();
+        db.record_paper_fill("main", "mint", "buy", 100_000_000, 5_000).unwrap();
+        db.record_paper_fill("main", "mint", "sell", 60_000_000, 2_000).unwrap();
+        db.record_paper_fill("alt", "mint", "buy", 100_000_000, 7_000).unwrap();
+        assert_eq!(db.paper_token_balance("main", "mint").unwrap(), 3_000);
+        assert_eq!(db.paper_token_balance("alt", "mint").unwrap(), 7_000);
+        assert_eq!(db.paper_token_balance("main", "other").unwrap(), 0);
+
+        // Sold-out mints aren't holdings
+        db.record_paper_fill("main", "gone", "buy", 10_000_000, 1_000).unwrap();
+        db.record_paper_fill("main", "gone", "sell", 12_000_000, 1_000).unwrap();
+        assert_eq!(db.paper_holdings("main").unwrap(), vec![("mint".to_string(), 3_000)]);
+    }
+
+    #[test]
+    fn test_sizing_history() {
+        let temp_dir = tempdir().unwrap();
+        let db = ProfitDatabase::new(temp_dir.path().join("sizing.db").to_str().unwrap()).unwrap();
+        db.record_trade(&trade("main", 3.0, true)).unwrap();
+        db.record_trade(&trade("main", -0.5, true)).unwrap();
+        assert_eq!(db.source_record("chan", true).unwrap(), (2, 1));
+        assert_eq!(db.recent_returns(true, 10).unwrap(), vec![-0.5, 3.0]);
+        // Paper history doesn't size live buys
+        assert_eq!(db.source_record("chan", false).unwrap(), (0, 0));
+        assert!(db.recent_returns(false, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_wallet_pnl() {
+        let temp_dir = tempdir().unwrap();
+        let db = ProfitDatabase::new(temp_dir.path().join("wallets.db").to_str().unwrap()).unwrap();
+        db.record_trade(&trade("alt", 0.25, false)).unwrap();
+        db.record_trade(&trade("main", -0.1, false)).unwrap();
+        db.record_trade(&trade("main", 0.3, false)).unwrap();
+        let pnl = db.wallet_pnl().unwrap();
+        assert_eq!(pnl[0], ("alt".to_string(), 0.25, 1));
+        assert_eq!((pnl[1].0.as_str(), pnl[1].2), ("main", 2));
+        assert!((pnl[1].1 - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_partial_exits_count_as_one_position() {
+        let temp_dir = tempdir().unwrap();
+        let db = ProfitDatabase::new(temp_dir.path().join("exits.db").to_str().unwrap()).unwrap();
+        db.record_trade(&trade("main", -0.1, false)).unwrap();
+        // A partial take profit and the final exit
+        let exit = TradeRecord { cost_sol: 0.5, position_id: Some(7), ..trade("main", 1.0, false) };
+        db.record_trade(&exit).unwrap();
+        db.record_trade(&TradeRecord { pnl: -0.6, ..exit }).unwrap();
+        assert_eq!(db.source_record("chan", false).unwrap(), (2, 1));
+        assert_eq!(db.recent_returns(false, 1).unwrap(), vec![0.4]);
+    }
+
+    #[test]
+    fn test_recent_trades_span_days() {
+        let temp_dir = tempdir().unwrap();
+        let db = ProfitDatabase::new(temp_dir.path().join("recent.db").to_str().unwrap()).unwrap();
+        db.record_trade(&trade("main", -0.1, false)).unwrap();
+        db.conn.lock().unwrap().execute("UPDATE trades SET created_at = datetime('now', '-1 day')", []).unwrap();
+        db.record_trade(&trade("main", -0.2, false)).unwrap();
+        db.record_trade(&trade("main", 0.3, true)).unwrap();
+
+        assert_eq!(db.todays_trades(false, 0).unwrap(), vec![(2, -0.2)]);
+        assert_eq!(db.recent_trades(false, 0, 10).unwrap(), vec![(2, -0.2), (1, -0.1)]);
+        assert_eq!(db.recent_trades(false, 0, 1).unwrap(), vec![(2, -0.2)]);
+        assert!(db.recent_trades(false, 2, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_positions_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let db = ProfitDatabase::new(temp_dir.path().join("positions.db").to_str().unwrap()).unwrap();
+        let mut position = PositionRecord {
+            id: 0,
+            wallet: "main".to_string(),
+            mint: "mint".to_string(),
+            source: "chan".to_string(),
+            paper: false,
+            cost_sol: 0.1,
+            remaining_cost: 0.1,
+            fill: Some(Fill { sol_spent: 100_000_000, fee: 5_000, rent: 2_039_280, tokens: 1_000_000 }),
+            entry_price: None,
+            stop_price: None,
+            peak_price: None,
+            hit_breakeven: false,
+            took_partial_profit: false,
+            status: POSITION_OPEN.to_string(),
+        };
+        position.id = db.insert_position(&position).unwrap();
+        assert_eq!(db.open_positions(false).unwrap(), vec![position.clone()]);
+        assert!(db.open_positions(true).unwrap().is_empty());
+
+        position.entry_price = Some(3e-8);
+        position.stop_price = Some(3e-8);
+        position.peak_price = Some(9e-8);
+        position.hit_breakeven = true;
+        position.remaining_cost = 0.05;
+        db.update_position(&position).unwrap();
+        assert_eq!(db.open_positions(false).unwrap(), vec![position.clone()]);
+
+        db.set_position_status(position.id, POSITION_CLOSED).unwrap();
+        assert!(db.open_positions(false).unwrap().is_empty());
+        assert_eq!(db.position_status(position.id).unwrap().as_deref(), Some(POSITION_CLOSED));
+        // A manager still holding the position can't reopen it
+        db.update_position(&position).unwrap();
+        assert_eq!(db.position_status(position.id).unwrap().as_deref(), Some(POSITION_CLOSED));
+
+        assert!(!db.flag("paused").unwrap());
+        db.set_flag("paused", true).unwrap();
+        assert!(db.flag("paused").unwrap());
+        db.set_flag("paused", false).unwrap();
+        assert!(!db.flag("paused").unwrap());
+    }
+}
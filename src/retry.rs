@@ -0,0 +1,123 @@
+use serde::Deserialize;
+use std::time::Duration;
+use crate::Config;
+
+/// Retry ladder for failed buys (`[retry]` table in config.toml)
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// Total attempts per buy, including the first
+    pub max_attempts: u32,
+    /// Slippage added on each retry, capped at `max_slippage_bps`
+    pub slippage_step_bps: u16,
+    pub max_slippage_bps: u16,
+    /// Priority fee multiplier per retry, capped at `max_priority_fee_microlamports`
+    pub fee_multiplier: f64,
+    pub max_priority_fee_microlamports: u64,
+    /// No attempt starts later than this after the signal
+    pub deadline_secs: u64,
+    /// Stop retrying once the price is this much above where it was at the signal
+    pub max_chase_pct: f64,
+    pub backoff_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            slippage_step_bps: 200,
+            max_slippage_bps: 2_000,
+            fee_multiplier: 1.5,
+            max_priority_fee_microlamports: 2_000_000,
+            deadline_secs: 20,
+            max_chase_pct: 15.0,
+            backoff_ms: 300,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Config for a 0-based attempt with slippage and priority fee escalated
+    pub fn attempt_config(&self, base: &Config, attempt: u32) -> Config {
+        escalate(base, attempt, self.slippage_step_bps, self.max_slippage_bps, self.fee_multiplier, self.max_priority_fee_microlamports)
+    }
+
+    pub fn backoff(&self) -> Duration {
+        Duration::from_millis(self.backoff_ms)
+    }
+}
+
+/// `base` with slippage stepped up and the priority fee multiplied `attempt` times, each capped
+/// (a cap below the base setting never lowers it)
+pub fn escalate(base: &Config, attempt: u32, slippage_step_bps: u16, max_slippage_bps: u16, fee_multiplier: f64, max_fee: u64) -> Config {
+    let mut cfg = base.clone();
+    if attempt == 0 {
+        return cfg;
+    }
+    let bumped = (base.slippage_bps as u32).saturating_add((slippage_step_bps as u32).saturating_mul(attempt));
+    cfg.slippage_bps = bumped.min(max_slippage_bps.max(base.slippage_bps) as u32) as u16;
+    let fee = base.priority_fee_microlamports as f64 * fee_multiplier.max(1.0).powi(attempt as i32);
+    cfg.priority_fee_microlamports = (fee as u64).min(max_fee.max(base.priority_fee_microlamports));
+    cfg
+}
+
+/// A sent transaction whose outcome couldn't be settled; it may have landed, so it must not be resent
+#[derive(Debug)]
+pub struct MaybeLanded(pub String);
+
+impl std::fmt::Display for MaybeLanded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "may have landed: {}", self.0)
+    }
+}
+
+impl std::error::Error for MaybeLanded {}
+
+/// Whether another attempt could succeed: guard rejections and empty wallets won't change,
+/// and a transaction that may have landed must not be doubled up
+pub fn is_retryable(err: &anyhow::Error) -> bool {
+    if err.downcast_ref::<crate::guards::GuardViolation>().is_some() || err.downcast_ref::<MaybeLanded>().is_some() {
+        return false;
+    }
+    let msg = err.to_string().to_lowercase();
+    !msg.contains("insufficient funds") && !msg.contains("insufficient lamports")
+}
+
+/// How far the price has run since the signal, from the tokens the liquidity probe buys
+pub fn chase_pct(probe_at_signal: u64, probe_now: u64) -> f64 {
+    if probe_at_signal == 0 {
+        return 0.0;
+    }
+    // Fewer tokens for the same SOL means a higher price
+    (probe_at_signal as f64 / probe_now.max(1) as f64 - 1.0) * 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attempt_config_escalates_and_caps() {
+        let base = crate::test_config();
+        let retry = RetryConfig { slippage_step_bps: 500, max_slippage_bps: 1_200, fee_multiplier: 2.0, max_priority_fee_microlamports: 300_000, ..Default::default() };
+        let first = retry.attempt_config(&base, 0);
+        assert_eq!((first.slippage_bps, first.priority_fee_microlamports), (500, 100_000));
+        let second = retry.attempt_config(&base, 1);
+        assert_eq!((second.slippage_bps, second.priority_fee_microlamports), (1_000, 200_000));
+        let third = retry.attempt_config(&base, 2);
+        assert_eq!((third.slippage_bps, third.priority_fee_microlamports), (1_200, 300_000));
+    }
+
+    #[test]
+    fn test_retryable_and_chase() {
+        assert!(is_retryable(&anyhow::anyhow!("custom program error: 0x1771")));
+        assert!(!is_retryable(&anyhow::anyhow!("Attempt to debit an account but found no record of a prior credit. Insufficient funds")));
+        let guard = crate::guards::GuardViolation::PriceImpact { impact_pct: 30.0, max_pct: 10.0 };
+        assert!(!is_retryable(&anyhow::Error::new(guard)));
+        assert!(!is_retryable(&anyhow::Error::new(MaybeLanded("unable to confirm transaction".to_string()))));
+
+        assert_eq!(chase_pct(1_000, 1_000), 0.0);
+        assert!((chase_pct(1_000, 800) - 25.0).abs() < 1e-9);
+        assert!(chase_pct(1_000, 1_100) < 0.0);
+    }
+}
@@ -0,0 +1,289 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::UiTransactionEncoding;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use crate::token::MintInfo;
+use crate::Config;
+
+/// Holder concentration and creator checks (`[risk]` table in config.toml)
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RiskConfig {
+    pub enabled: bool,
+    /// Top-10 holder share (excluding pool/curve vaults) that scores the full weight
+    pub max_top10_pct: f64,
+    /// Creator share that scores the full weight
+    pub max_creator_pct: f64,
+    /// Top holders whose token accounts were opened in the same slot before it counts as a bundle
+    pub bundle_min_wallets: usize,
+    /// Scores at or above this skip the buy
+    pub block_score: u8,
+    /// Scores at or above this scale the position by `shrink_factor`
+    pub shrink_score: u8,
+    pub shrink_factor: f64,
+    /// Time allowed for the creator and bundle lookups; one that runs over is skipped
+    pub lookup_budget_ms: u64,
+}
+
+impl Default for RiskConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_top10_pct: 30.0,
+            max_creator_pct: 5.0,
+            bundle_min_wallets: 3,
+            block_score: 70,
+            shrink_score: 40,
+            shrink_factor: 0.5,
+            lookup_budget_ms: 3_000,
+        }
+    }
+}
+
+/// Top holders counted towards concentration
+const TOP_HOLDERS: usize = 10;
+
+/// One of the largest token accounts of a mint
+#[derive(Debug, Clone)]
+pub struct Holder {
+    pub token_account: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+impl Holder {
+    /// Pools and bonding curves hold their tokens in accounts owned by a PDA
+    pub fn is_vault(&self) -> bool {
+        !self.owner.is_on_curve()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RiskReport {
+    pub top10_pct: f64,
+    pub creator: Option<Pubkey>,
+    pub creator_pct: f64,
+    /// Slot and number of top holders whose token accounts were opened in it
+    pub bundle: Option<(u64, usize)>,
+    pub score: u8,
+}
+
+impl fmt::Display for RiskReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "risk {}/100 | top10 {:.1}% | creator {:.1}%", self.score, self.top10_pct, self.creator_pct)?;
+        if let Some((slot, wallets)) = self.bundle {
+            write!(f, " | bundled: {} wallets in slot {}", wallets, slot)?;
+        }
+        Ok(())
+    }
+}
+
+/// What the risk score means for a buy
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RiskAction {
+    Proceed,
+    Shrink(f64),
+    Block,
+}
+
+/// Percent of supply held by the largest non-vault holders
+pub fn top_holder_pct(holders: &[Holder], supply: u64) -> f64 {
+    if supply == 0 {
+        return 0.0;
+    }
+    let held: u64 = holders.iter().filter(|h| !h.is_vault()).take(TOP_HOLDERS).map(|h| h.amount).sum();
+    held as f64 / supply as f64 * 100.0
+}
+
+/// The slot in which the most holder token accounts were opened, if at least `min_wallets` share it.
+/// Accounts are opened by their first buy, so this catches wallets that bought in the same block.
+pub fn detect_bundle(open_slots: &[u64], min_wallets: usize) -> Option<(u64, usize)> {
+    let mut counts: HashMap<u64, usize> = HashMap::new();
+    for slot in open_slots {
+        *counts.entry(*slot).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .filter(|(_, count)| *count >= min_wallets.max(2))
+        .max_by_key(|(slot, count)| (*count, std::cmp::Reverse(*slot)))
+}
+
+/// 0-100: up to 40 for concentration, 30 for creator holdings and 30 for a bundled launch
+pub fn score(risk: &RiskConfig, report: &RiskReport) -> u8 {
+    let weight = |value: f64, max: f64| if max > 0.0 { (value / max).clamp(0.0, 2.0) } else { 0.0 };
+    let mut points = weight(report.top10_pct, risk.max_top10_pct) * 20.0;
+    points += weight(report.creator_pct, risk.max_creator_pct) * 15.0;
+    if report.bundle.is_some() {
+        points += 30.0;
+    }
+    points.round().min(100.0) as u8
+}
+
+pub fn action(risk: &RiskConfig, score: u8) -> RiskAction {
+    if score >= risk.block_score {
+        RiskAction::Block
+    } else if score >= risk.shrink_score {
+        RiskAction::Shrink(risk.shrink_factor)
+    } else {
+        RiskAction::Proceed
+    }
+}
+
+/// Signatures fetched per page, the RPC maximum
+const SIGNATURE_PAGE: usize = 1000;
+/// Pages walked back before giving up on finding an address's first transaction;
+/// the accounts of a fresh launch rarely have more
+const MAX_SIGNATURE_PAGES: usize = 3;
+
+/// Oldest signature touching `address`, paging back through its whole history
+async fn first_signature(rpc: &RpcClient, address: &Pubkey) -> Result<Option<(Signature, u64)>> {
+    let mut before = None;
+    let mut oldest = None;
+    for _ in 0..MAX_SIGNATURE_PAGES {
+        let config = GetConfirmedSignaturesForAddress2Config { before, limit: Some(SIGNATURE_PAGE), ..Default::default() };
+        let page = rpc.get_signatures_for_address_with_config(address, config).await?;
+        let Some(last) = page.last() else {
+            return Ok(oldest);
+        };
+        let signature = Signature::from_str(&last.signature)?;
+        oldest = Some((signature, last.slot));
+        if page.len() < SIGNATURE_PAGE {
+            return Ok(oldest);
+        }
+        before = Some(signature);
+    }
+    // Anything older is out of reach; a guess would pin the result on some later trader
+    Err(anyhow!("{} has more than {} transactions, its first is out of reach", address, SIGNATURE_PAGE * MAX_SIGNATURE_PAGES))
+}
+
+/// Creator of `mint`: the launchpad curve's creator field, else the fee payer of its creation transaction
+pub async fn find_creator(rpc: &RpcClient, mint: &Pubkey) -> Result<Option<Pubkey>> {
+    if let Ok((launchpad, curve)) = crate::curve::find_curve(rpc, mint).await {
+        let account = rpc.get_account(&curve).await?;
+        if let Some(creator) = crate::curve::decode(launchpad, &account.data)?.creator {
+            return Ok(Some(creator));
+        }
+    }
+    let Some((signature, _)) = first_signature(rpc, mint).await? else {
+        return Ok(None);
+    };
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(rpc.commitment()),
+        max_supported_transaction_version: Some(0),
+    };
+    let tx = rpc.get_transaction_with_config(&signature, config).await?;
+    let decoded = tx.transaction.transaction.decode().ok_or(anyhow!("Could not decode creation tx {}", signature))?;
+    Ok(decoded.message.static_account_keys().first().copied())
+}
+
+/// Largest token accounts with their owners resolved
+pub async fn largest_holders(rpc: &RpcClient, mint: &Pubkey) -> Result<Vec<Holder>> {
+    let largest = rpc.get_token_largest_accounts(mint).await?;
+    let addresses = largest.iter().map(|b| Pubkey::from_str(&b.address)).collect::<Result<Vec<_>, _>>()?;
+    let accounts = rpc.get_multiple_accounts(&addresses).await?;
+    let mut holders = Vec::new();
+    for ((balance, token_account), account) in largest.iter().zip(addresses).zip(accounts) {
+        // Token account owner sits right after the mint
+        let Some(owner) = account.and_then(|a| a.data.get(32..64).map(|d| Pubkey::new_from_array(d.try_into().unwrap()))) else {
+            continue;
+        };
+        holders.push(Holder { token_account, owner, amount: balance.amount.amount.parse().unwrap_or(0) });
+    }
+    Ok(holders)
+}
+
+/// Slots the top non-vault holders' token accounts were opened in, looked up together
+async fn open_slots(rpc: &RpcClient, holders: &[Holder]) -> Vec<u64> {
+    // A token account's first transaction is the one that opened it
+    let lookups = holders.iter().filter(|h| !h.is_vault()).take(TOP_HOLDERS).map(|h| first_signature(rpc, &h.token_account));
+    futures_util::future::join_all(lookups)
+        .await
+        .into_iter()
+        .filter_map(|first| first.ok().flatten().map(|(_, slot)| slot))
+        .collect()
+}
+
+/// Gather holder data for `mint` and score it
+pub async fn assess(cfg: &Config, rpc: &RpcClient, mint: &Pubkey, mint_info: &MintInfo) -> Result<RiskReport> {
+    let holders = largest_holders(rpc, mint).await?;
+    let mut report = RiskReport { top10_pct: top_holder_pct(&holders, mint_info.supply), ..Default::default() };
+
+    // History lookups can crawl on busy accounts; a buy shouldn't wait on them
+    let budget = Duration::from_millis(cfg.risk.lookup_budget_ms);
+    let (creator, slots) = tokio::join!(
+        tokio::time::timeout(budget, find_creator(rpc, mint)),
+        tokio::time::timeout(budget, open_slots(rpc, &holders)),
+    );
+
+    match creator.unwrap_or_else(|_| Err(anyhow!("lookup took over {} ms", cfg.risk.lookup_budget_ms))) {
+        Ok(Some(creator)) => {
+            let ata = crate::token::associated_token_address(&creator, mint, &mint_info.program);
+            let held = crate::token::token_balance(rpc, &ata).await.unwrap_or(0);
+            report.creator = Some(creator);
+            if mint_info.supply > 0 {
+                report.creator_pct = held as f64 / mint_info.supply as f64 * 100.0;
+            }
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Could not find creator of {}: {}", mint, e),
+    }
+
+    match slots {
+        Ok(slots) => report.bundle = detect_bundle(&slots, cfg.risk.bundle_min_wallets),
+        Err(_) => tracing::warn!("Skipping the bundle check for {}: lookups took over {} ms", mint, cfg.risk.lookup_budget_ms),
+    }
+    report.score = score(&cfg.risk, &report);
+    tracing::info!("🧮 {}: {}", mint, report);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signer::{keypair::Keypair, Signer};
+
+    fn holder(owner: Pubkey, amount: u64) -> Holder {
+        Holder { token_account: Pubkey::new_unique(), owner, amount }
+    }
+
+    #[test]
+    fn test_top_holder_pct_skips_vaults() {
+        let wallet = Keypair::new().pubkey();
+        let (curve, _) = Pubkey::find_program_address(&[b"bonding-curve"], &Pubkey::new_unique());
+        let holders = vec![holder(curve, 800), holder(wallet, 100), holder(wallet, 50)];
+        assert!((top_holder_pct(&holders, 1_000) - 15.0).abs() < 1e-9);
+        assert_eq!(top_holder_pct(&holders, 0), 0.0);
+    }
+
+    #[test]
+    fn test_detect_bundle() {
+        assert_eq!(detect_bundle(&[10, 11, 12, 13], 3), None);
+        assert_eq!(detect_bundle(&[10, 20, 20, 30, 20], 3), Some((20, 3)));
+        assert_eq!(detect_bundle(&[5, 5, 9, 9], 2), Some((5, 2)));
+    }
+
+    #[test]
+    fn test_score_and_action() {
+        let risk = RiskConfig::default();
+        let clean = RiskReport { top10_pct: 6.0, creator_pct: 0.0, ..Default::default() };
+        assert_eq!(score(&risk, &clean), 4);
+        assert_eq!(action(&risk, score(&risk, &clean)), RiskAction::Proceed);
+
+        let concentrated = RiskReport { top10_pct: 45.0, creator_pct: 5.0, ..Default::default() };
+        assert_eq!(score(&risk, &concentrated), 45);
+        assert_eq!(action(&risk, 45), RiskAction::Shrink(0.5));
+
+        let bundled = RiskReport { bundle: Some((1, 4)), ..concentrated };
+        assert_eq!(score(&risk, &bundled), 75);
+        assert_eq!(action(&risk, 75), RiskAction::Block);
+    }
+}
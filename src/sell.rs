@@ -2,55 +2,250 @@ use anyhow::Result;
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{pubkey::Pubkey /*, transaction::VersionedTransaction */};
 use std::str::FromStr;
-use std::sync::Arc;
-use solana_sdk::{signer::keypair::Keypair, signer::Signer};
+use solana_sdk::signer::Signer;
 use crate::{Config, get_sol_balance};
+use crate::profit_db::ProfitDatabase;
+use crate::wallets::{Wallet, WalletPool};
 
-pub async fn execute(mint: Pubkey, cfg: Config, payer: Arc<Keypair>) -> Result<()> {
+/// How much of the token balance a sell should dispose of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SellAmount {
+    All,
+    Percent(u8),
+    Raw(u64),
+}
+
+impl SellAmount {
+    /// Resolve to a raw token amount given the current balance
+    pub fn resolve(&self, balance: u64) -> u64 {
+        match *self {
+            SellAmount::All => balance,
+            SellAmount::Percent(pct) => (balance as u128 * pct.min(100) as u128 / 100) as u64,
+            SellAmount::Raw(amount) => amount.min(balance),
+        }
+    }
+}
+
+impl FromStr for SellAmount {
+    type Err = anyhow::Error;
+
+    /// Accepts `all`, `50%` or a raw token amount
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_lowercase();
+        if s == "all" || s == "100%" {
+            return Ok(SellAmount::All);
+        }
+        if let Some(pct) = s.strip_suffix('%') {
+            let pct: u8 = pct.trim().parse()?;
+            if pct == 0 || pct > 100 {
+                return Err(anyhow::anyhow!("Percent must be between 1 and 100"));
+            }
+            return Ok(SellAmount::Percent(pct));
+        }
+        Ok(SellAmount::Raw(s.parse()?))
+    }
+}
+
+impl std::fmt::Display for SellAmount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SellAmount::All => write!(f, "all"),
+            SellAmount::Percent(pct) => write!(f, "{}%", pct),
+            SellAmount::Raw(amount) => write!(f, "{} tokens", amount),
+        }
+    }
+}
+
+/// Where a sell is routed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Venue {
+    Jupiter,
+    /// PumpPortal's local trade API, the fallback when Jupiter keeps failing
+    PumpPortal,
+}
+
+impl std::fmt::Display for Venue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Venue::Jupiter => write!(f, "Jupiter"),
+            Venue::PumpPortal => write!(f, "PumpPortal"),
+        }
+    }
+}
+
+/// What a sell actually did
+#[derive(Debug, Clone)]
+pub struct SellOutcome {
+    pub tokens_sold: u64,
+    /// Token-2022 transfer fee withheld from the tokens we sent
+    pub transfer_fee: u64,
+    /// Quoted SOL for the tokens that reach the pool after the transfer fee
+    pub expected_sol: Option<f64>,
+    /// SOL the sell transaction paid the wallet, net of its network fees
+    pub sol_received: f64,
+}
+
+pub async fn execute(mint: Pubkey, sell_amount: SellAmount, cfg: Config, wallet: Wallet, db: ProfitDatabase) -> Result<SellOutcome> {
+    execute_via(mint, sell_amount, cfg, wallet, db, Venue::Jupiter).await
+}
+
+pub async fn execute_via(mint: Pubkey, sell_amount: SellAmount, cfg: Config, wallet: Wallet, db: ProfitDatabase, venue: Venue) -> Result<SellOutcome> {
+    if cfg.is_paper() {
+        return crate::paper::sell(&wallet.name, mint, sell_amount, &cfg, &db).await;
+    }
+    let payer = wallet.keypair.clone();
     let rpc = RpcClient::new(cfg.rpc_http.clone());
-    tracing::info!("Selling {}", mint);
+    tracing::info!("Selling {} of {} from wallet {} via {}", sell_amount, mint, wallet.name, venue);
 
-    let token_program = Pubkey::from_str("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA")?;
-    let ata_program = Pubkey::from_str("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL")?;
-    let token_account = Pubkey::find_program_address(
-        &[&payer.pubkey().to_bytes(), token_program.as_ref(), mint.as_ref()],
-        &ata_program
-    ).0;
+    let mint_info = crate::token::fetch_mint(&rpc, &mint).await?;
+    let token_account = crate::token::associated_token_address(&payer.pubkey(), &mint, &mint_info.program);
 
-    let balance_resp = rpc.get_token_account_balance(&token_account).await?;
-    let amount = balance_resp.amount.parse::<u64>()?;
+    let balance = crate::token::token_balance(&rpc, &token_account).await?;
+    let amount = sell_amount.resolve(balance);
     if amount == 0 {
         tracing::info!("No balance to sell for {}", mint);
-        return Ok(());
+        return Ok(SellOutcome { tokens_sold: 0, transfer_fee: 0, expected_sol: None, sol_received: 0.0 });
     }
     let sol_mint = Pubkey::from_str("So11111111111111111111111111111111111111112")?;
-    let mut tx = crate::swap::get_swap_transaction(&cfg, &payer.pubkey(), mint, sol_mint, amount).await?;
-    let bh = rpc.get_latest_blockhash().await?;
-    let mut message = tx.message.clone();
-    match &mut message {
-        solana_sdk::message::VersionedMessage::Legacy(ref mut msg) => {
-            msg.recent_blockhash = bh;
-        }
-        solana_sdk::message::VersionedMessage::V0(ref mut msg) => {
-            msg.recent_blockhash = bh;
-        }
-    }
-    let message_hash = message.hash();
-    let sig = payer.sign_message(message_hash.as_ref());
-    tx.message = message;
-    tx.signatures = vec![sig];
-    let signature = rpc.send_and_confirm_transaction(&tx).await?;
+
+    // Token-2022 fees are withheld on transfer, so the pool only receives amount - fee
+    let transfer_fee = if mint_info.transfer_fee.is_some() {
+        let epoch = rpc.get_epoch_info().await?.epoch;
+        mint_info.transfer_fee(epoch, amount)
+    } else {
+        0
+    };
+    // The quote is informational, so a Jupiter outage mustn't block the exit
+    let expected_sol = if transfer_fee > 0 {
+        match crate::swap::get_quote(&cfg, mint, sol_mint, amount - transfer_fee, cfg.slippage_bps).await {
+            Ok(quote) => {
+                let out = quote["outAmount"].as_str().and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
+                tracing::info!("Transfer fee on {}: {} tokens withheld, expected proceeds {:.6} SOL", mint, transfer_fee, out as f64 / 1e9);
+                Some(out as f64 / 1e9)
+            }
+            Err(e) => {
+                tracing::warn!("Could not quote {} after its transfer fee: {}", mint, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let fully_exited = amount == balance;
+    // Token-2022 accounts holding withheld fees can't be closed, so leave those to the sweeper
+    let close_after = fully_exited && cfg.close_empty_accounts && mint_info.transfer_fee.is_none();
+    let close_in_tx = close_after && cfg.jupiter.use_swap_instructions && venue == Venue::Jupiter;
+    let mut extras = if close_in_tx {
+        crate::swap::ExtraInstructions::close(token_account, mint_info.program)
+    } else {
+        crate::swap::ExtraInstructions::default()
+    };
+    // A nonce-signed exit stays valid through congestion, so it can be rebroadcast until it lands
+    let lease = if cfg.nonce.enabled && cfg.jupiter.use_swap_instructions && venue == Venue::Jupiter {
+        let lease = crate::nonce::NonceLease::acquire(&payer.pubkey(), cfg.nonce.accounts);
+        if lease.is_none() {
+            tracing::warn!("No free nonce account for {}, selling {} with a recent blockhash", wallet.name, mint);
+        }
+        lease
+    } else {
+        None
+    };
+    if let Some(lease) = &lease {
+        let nonce = crate::nonce::fetch_nonce(&rpc, &lease.account).await?;
+        extras = extras.with_nonce(lease.account, payer.pubkey(), nonce);
+    }
+    let tx = match venue {
+        Venue::Jupiter => crate::swap::build_swap_transaction(&cfg, &rpc, &payer, mint, sol_mint, amount, &extras).await?,
+        Venue::PumpPortal => {
+            let ui_amount = if fully_exited {
+                "100%".to_string()
+            } else {
+                (amount as f64 / 10f64.powi(mint_info.decimals as i32)).to_string()
+            };
+            // PumpPortal takes a total priority fee in SOL rather than a per-CU price
+            let compute_units = cfg.jupiter.compute_unit_limit.unwrap_or(200_000) as f64;
+            let priority_fee_sol = cfg.priority_fee_microlamports as f64 * compute_units / 1e6 / 1e9;
+            crate::swap::pumpportal_sell_transaction(&cfg.exit.pumpportal_url, &payer, &mint, &ui_amount, cfg.slippage_bps, priority_fee_sol).await?
+        }
+    };
+    let signature = match &lease {
+        Some(lease) => {
+            // Stop rebroadcasting once something else has sold these tokens
+            let still_wanted = || async { crate::token::token_balance(&rpc, &token_account).await.map_or(true, |b| b >= amount) };
+            crate::nonce::send_until_landed(&cfg, &rpc, &tx, &lease.account, still_wanted).await?
+        }
+        None => rpc.send_and_confirm_transaction(&tx).await?,
+    };
+    drop(lease);
+
+    // Prebuilt Jupiter transactions can't carry our close, so send it separately
+    if close_after && !close_in_tx {
+        let empty = crate::sweeper::EmptyAccount { address: token_account, program: mint_info.program, lamports: 0 };
+        let report = crate::sweeper::close_accounts(&rpc, &payer, &[empty]).await;
+        if report.closed == 0 {
+            tracing::warn!("Could not close emptied token account {} for {}", token_account, mint);
+        }
+    }
+    
+    // Proceeds come from this transaction alone; the wallet balance also moves with any other trade in flight
+    let sol_received = match crate::fills::fetch_sale(&rpc, &signature, &payer.pubkey(), &mint).await {
+        Ok(sale) => sale.sol_received_f64(),
+        Err(e) => {
+            let msg = format!("⚠️ Could not read the proceeds of selling {} from {} (TX: {}): {}; booking it at 0 SOL", mint, wallet.name, signature, e);
+            tracing::warn!("{}", msg);
+            crate::notifier::log(msg).await;
+            0.0
+        }
+    };
     
     // Check balance after successful sale
     match get_sol_balance(&cfg.rpc_http, &payer.pubkey()).await {
         Ok(new_balance) => {
             tracing::info!("💰 Balance after selling {}: {:.4} SOL", mint, new_balance);
-            crate::notifier::log(format!("🔴 SOLD {} | TX: {} | Balance: {:.4} SOL", mint, signature, new_balance)).await;
+            crate::notifier::log(format!("🔴 SOLD {} ({}) from {} for {:+.4} SOL | TX: {} | Balance: {:.4} SOL", mint, sell_amount, wallet.name, sol_received, signature, new_balance)).await;
         }
         Err(e) => {
             tracing::warn!("Could not check balance after sale: {}", e);
-            crate::notifier::log(format!("🔴 SOLD {} - TX: {}", mint, signature)).await;
+            crate::notifier::log(format!("🔴 SOLD {} ({}) from {} for {:+.4} SOL - TX: {}", mint, sell_amount, wallet.name, sol_received, signature)).await;
         }
     }
-    Ok(())
-}
\ No newline at end of file
+    Ok(SellOutcome { tokens_sold: amount, transfer_fee, expected_sol, sol_received })
+}
+
+/// Sell `mint` out of every wallet concurrently; wallets that don't hold it sell nothing
+pub async fn execute_all_wallets(
+    mint: Pubkey,
+    sell_amount: SellAmount,
+    cfg: Config,
+    wallets: &WalletPool,
+    db: ProfitDatabase,
+) -> Vec<(String, Result<SellOutcome>)> {
+    let sells = wallets.all().iter().map(|wallet| {
+        let name = wallet.name.clone();
+        let sell = execute(mint, sell_amount, cfg.clone(), wallet.clone(), db.clone());
+        async move { (name, sell.await) }
+    });
+    futures_util::future::join_all(sells).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sell_amount_resolve_and_parse() {
+        assert_eq!(SellAmount::All.resolve(1_000), 1_000);
+        assert_eq!(SellAmount::Percent(50).resolve(1_001), 500);
+        assert_eq!(SellAmount::Percent(100).resolve(u64::MAX), u64::MAX);
+        assert_eq!(SellAmount::Raw(5_000).resolve(1_000), 1_000);
+
+        assert_eq!("all".parse::<SellAmount>().unwrap(), SellAmount::All);
+        assert_eq!("100%".parse::<SellAmount>().unwrap(), SellAmount::All);
+        assert_eq!(" 25% ".parse::<SellAmount>().unwrap(), SellAmount::Percent(25));
+        assert_eq!("123456".parse::<SellAmount>().unwrap(), SellAmount::Raw(123_456));
+        assert!("0%".parse::<SellAmount>().is_err());
+        assert!("150%".parse::<SellAmount>().is_err());
+        assert!("half".parse::<SellAmount>().is_err());
+    }
+}
@@ -0,0 +1,254 @@
+use anyhow::Result;
+use serde::Deserialize;
+use solana_account_decoder::UiAccountData;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_request::TokenAccountsFilter;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use crate::Config;
+use crate::profit_db::{ProfitDatabase, TradeRecord, POSITION_CLOSED};
+use crate::sell::{SellAmount, SellOutcome, Venue};
+use crate::token::{TOKEN_2022_PROGRAM, TOKEN_PROGRAM};
+use crate::wallets::{Wallet, WalletPool};
+
+const WSOL_MINT: &str = "So11111111111111111111111111111111111111112";
+
+/// Emergency flatten settings (`[sell_all]` table in config.toml)
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct SellAllConfig {
+    /// Floor for slippage and priority fee; the normal settings win if they're higher
+    pub slippage_bps: u16,
+    pub priority_fee_microlamports: u64,
+    /// How long a Telegram `/sell_all` waits for `/sell_all confirm`
+    pub confirm_secs: u64,
+}
+
+impl Default for SellAllConfig {
+    fn default() -> Self {
+        Self { slippage_bps: 3_000, priority_fee_microlamports: 1_000_000, confirm_secs: 60 }
+    }
+}
+
+/// `cfg` with slippage and priority fee raised to at least the sell-all floor
+pub fn aggressive(cfg: &Config) -> Config {
+    let mut cfg = cfg.clone();
+    cfg.slippage_bps = cfg.slippage_bps.max(cfg.sell_all.slippage_bps);
+    cfg.priority_fee_microlamports = cfg.priority_fee_microlamports.max(cfg.sell_all.priority_fee_microlamports);
+    cfg
+}
+
+/// Mint of a jsonParsed token account that still holds something other than wrapped SOL
+fn held_mint(info: &serde_json::Value) -> Option<Pubkey> {
+    let amount: u64 = info["tokenAmount"]["amount"].as_str()?.parse().ok()?;
+    let mint = info["mint"].as_str()?;
+    if amount == 0 || mint == WSOL_MINT {
+        return None;
+    }
+    Pubkey::from_str(mint).ok()
+}
+
+/// Every non-SOL mint the wallet holds a balance of, on paper or on-chain
+async fn held_mints(cfg: &Config, rpc: &RpcClient, wallet: &Wallet, db: &ProfitDatabase) -> Result<Vec<Pubkey>> {
+    let mut mints = Vec::new();
+    if cfg.is_paper() {
+        for (mint, _) in db.paper_holdings(&wallet.name)? {
+            mints.push(Pubkey::from_str(&mint)?);
+        }
+        return Ok(mints);
+    }
+    for program in [TOKEN_PROGRAM, TOKEN_2022_PROGRAM] {
+        let accounts = rpc.get_token_accounts_by_owner(&wallet.pubkey(), TokenAccountsFilter::ProgramId(program)).await?;
+        for keyed in accounts {
+            if let UiAccountData::Json(parsed) = &keyed.account.data {
+                if let Some(mint) = held_mint(&parsed.parsed["info"]) {
+                    if !mints.contains(&mint) {
+                        mints.push(mint);
+                    }
+                }
+            }
+        }
+    }
+    Ok(mints)
+}
+
+/// One aggressive Jupiter attempt, then PumpPortal if that's enabled
+async fn sell_everything_of(mint: Pubkey, cfg: Config, wallet: Wallet, db: ProfitDatabase) -> Result<SellOutcome> {
+    match crate::sell::execute_via(mint, SellAmount::All, cfg.clone(), wallet.clone(), db.clone(), Venue::Jupiter).await {
+        Err(e) if cfg.exit.pumpportal_fallback => {
+            tracing::warn!("Sell-all of {} from {} via Jupiter failed, trying PumpPortal: {}", mint, wallet.name, e);
+            crate::sell::execute_via(mint, SellAmount::All, cfg, wallet, db, Venue::PumpPortal).await
+        }
+        result => result,
+    }
+}
+
+/// Split `sol_received` over the positions a sell-all just closed, pro rata to the cost they had at risk
+pub fn split_proceeds(remaining_costs: &[f64], sol_received: f64) -> Vec<f64> {
+    let total: f64 = remaining_costs.iter().sum();
+    remaining_costs
+        .iter()
+        .map(|cost| if total > 0.0 { sol_received * cost / total } else { sol_received / remaining_costs.len() as f64 })
+        .collect()
+}
+
+/// Book the sell against the wallet's open positions in `mint` and close them, so their
+/// managers stop and a restart doesn't pick them up again
+fn close_positions(cfg: &Config, db: &ProfitDatabase, wallet: &str, mint: &Pubkey, outcome: &SellOutcome) -> Result<()> {
+    let mint = mint.to_string();
+    let positions: Vec<_> = db
+        .open_positions(cfg.is_paper())?
+        .into_iter()
+        .filter(|position| position.wallet == wallet && position.mint == mint)
+        .collect();
+    let costs: Vec<f64> = positions.iter().map(|position| position.remaining_cost).collect();
+    for (position, proceeds) in positions.iter().zip(split_proceeds(&costs, outcome.sol_received)) {
+        if outcome.tokens_sold > 0 {
+            let pnl = proceeds - position.remaining_cost;
+            db.record_trade(&TradeRecord {
+                wallet,
+                mint: &mint,
+                source: &position.source,
+                cost_sol: position.remaining_cost,
+                pnl,
+                paper: cfg.is_paper(),
+                position_id: Some(position.id),
+            })?;
+        }
+        db.set_position_status(position.id, POSITION_CLOSED)?;
+    }
+    Ok(())
+}
+
+/// What a sell-all did, per wallet and token
+#[derive(Debug, Default)]
+pub struct SellAllReport {
+    pub sells: Vec<(String, Pubkey, Result<SellOutcome>)>,
+    /// Wallets whose holdings couldn't be listed
+    pub scan_errors: Vec<(String, String)>,
+}
+
+impl SellAllReport {
+    pub fn failed(&self) -> usize {
+        self.sells.iter().filter(|(_, _, result)| result.is_err()).count() + self.scan_errors.len()
+    }
+
+    pub fn summary(&self) -> String {
+        let mut lines = Vec::new();
+        let mut received = 0.0;
+        for (wallet, mint, result) in &self.sells {
+            match result {
+                Ok(outcome) => {
+                    received += outcome.sol_received;
+                    lines.push(format!("✅ {} {}: sold {} tokens for {:+.4} SOL", wallet, mint, outcome.tokens_sold, outcome.sol_received));
+                }
+                Err(e) => lines.push(format!("❌ {} {}: {}", wallet, mint, e)),
+            }
+        }
+        for (wallet, e) in &self.scan_errors {
+            lines.push(format!("❌ {}: could not list tokens: {}", wallet, e));
+        }
+        if lines.is_empty() {
+            return "ℹ️ Sell-all: no tokens held".to_string();
+        }
+        let sold = self.sells.iter().filter(|(_, _, result)| result.is_ok()).count();
+        format!(
+            "🚨 Sell-all: {} sold, {} failed, {:+.4} SOL\n{}",
+            sold,
+            self.failed(),
+            received,
+            lines.join("\n")
+        )
+    }
+}
+
+/// Sell every token in every wallet at once with aggressive slippage and fees
+pub async fn run(cfg: &Config, wallets: &WalletPool, db: &ProfitDatabase) -> SellAllReport {
+    let rpc = RpcClient::new(cfg.rpc_http.clone());
+    let sell_cfg = aggressive(cfg);
+    let mut report = SellAllReport::default();
+    let mut sells = Vec::new();
+    for wallet in wallets.all() {
+        match held_mints(cfg, &rpc, wallet, db).await {
+            Ok(mints) => {
+                for mint in mints {
+                    let (cfg, wallet, db) = (cfg.clone(), wallet.clone(), db.clone());
+                    let sell = sell_everything_of(mint, sell_cfg.clone(), wallet.clone(), db.clone());
+                    sells.push(async move {
+                        let result = sell.await;
+                        if let Ok(outcome) = &result {
+                            if let Err(e) = close_positions(&cfg, &db, &wallet.name, &mint, outcome) {
+                                tracing::error!("Sold {} from {} but could not close its positions: {}", mint, wallet.name, e);
+                            }
+                        }
+                        (wallet.name.clone(), mint, result)
+                    });
+                }
+            }
+            Err(e) => report.scan_errors.push((wallet.name.clone(), e.to_string())),
+        }
+    }
+    tracing::warn!("🚨 Sell-all: selling {} token position(s)", sells.len());
+    report.sells = futures_util::future::join_all(sells).await;
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_held_mint() {
+        let mint = Pubkey::new_unique();
+        let info = |mint: &str, amount: &str| json!({ "mint": mint, "tokenAmount": { "amount": amount, "decimals": 6 } });
+        assert_eq!(held_mint(&info(&mint.to_string(), "42")), Some(mint));
+        assert_eq!(held_mint(&info(&mint.to_string(), "0")), None);
+        assert_eq!(held_mint(&info(WSOL_MINT, "1000")), None);
+        assert_eq!(held_mint(&json!({})), None);
+    }
+
+    #[test]
+    fn test_aggressive_and_summary() {
+        let cfg = aggressive(&crate::test_config());
+        assert_eq!((cfg.slippage_bps, cfg.priority_fee_microlamports), (3_000, 1_000_000));
+
+        let outcome = SellOutcome { tokens_sold: 10, transfer_fee: 0, expected_sol: None, sol_received: 0.25 };
+        let report = SellAllReport {
+            sells: vec![
+                ("main".to_string(), Pubkey::new_unique(), Ok(outcome)),
+                ("alt".to_string(), Pubkey::new_unique(), Err(anyhow::anyhow!("no route"))),
+            ],
+            scan_errors: vec![("third".to_string(), "rpc down".to_string())],
+        };
+        assert_eq!(report.failed(), 2);
+        let summary = report.summary();
+        assert!(summary.starts_with("🚨 Sell-all: 1 sold, 2 failed, +0.2500 SOL"));
+        assert!(summary.contains("no route") && summary.contains("rpc down"));
+        assert_eq!(SellAllReport::default().summary(), "ℹ️ Sell-all: no tokens held");
+    }
+
+    #[test]
+    fn test_close_positions_books_proceeds() {
+        assert_eq!(split_proceeds(&[1.0, 3.0], 8.0), vec![2.0, 6.0]);
+        assert_eq!(split_proceeds(&[0.0, 0.0], 0.5), vec![0.25, 0.25]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let db = ProfitDatabase::new(dir.path().join("sell_all.db").to_str().unwrap()).unwrap();
+        let cfg = crate::test_config();
+        let (mint, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let open = |wallet: &str, mint: &Pubkey, cost: f64| {
+            let record = crate::strategy::Position::new(*mint, "chan".to_string(), cost, None).record(wallet, false, crate::profit_db::POSITION_OPEN);
+            db.insert_position(&record).unwrap()
+        };
+        open("main", &mint, 0.5);
+        let kept = [open("alt", &mint, 0.5), open("main", &other, 0.5)];
+
+        let outcome = SellOutcome { tokens_sold: 10, transfer_fee: 0, expected_sol: None, sol_received: 0.75 };
+        close_positions(&cfg, &db, "main", &mint, &outcome).unwrap();
+        let still_open: Vec<i64> = db.open_positions(false).unwrap().iter().map(|position| position.id).collect();
+        assert_eq!(still_open, kept);
+        assert_eq!(db.source_record("chan", false).unwrap(), (1, 1));
+        assert!((db.get_profit().unwrap().total_profit - 0.25).abs() < 1e-9);
+    }
+}
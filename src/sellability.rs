@@ -0,0 +1,301 @@
+use serde::Deserialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSimulateTransactionConfig;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::{v0, VersionedMessage};
+use solana_sdk::packet::PACKET_DATA_SIZE;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+use std::fmt;
+use std::str::FromStr;
+use crate::token::MintInfo;
+use crate::Config;
+
+/// Pre-buy honeypot checks (`[sellability]` table in config.toml)
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct SellabilityConfig {
+    pub enabled: bool,
+    /// Max SOL lost buying and immediately selling back, fees and impact included
+    pub max_round_trip_loss_pct: f64,
+    /// Skip mints that still have a freeze authority
+    pub reject_freeze_authority: bool,
+}
+
+impl Default for SellabilityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_round_trip_loss_pct: 25.0,
+            reject_freeze_authority: true,
+        }
+    }
+}
+
+/// Why a token was judged unsellable
+#[derive(Debug, Clone, PartialEq)]
+pub enum Unsellable {
+    FreezeAuthority(Pubkey),
+    RestrictiveExtensions(Vec<&'static str>),
+    RoundTripFailed(String),
+    SimulationFailed(String),
+    RoundTripLoss { loss_pct: f64, max_pct: f64 },
+}
+
+impl fmt::Display for Unsellable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unsellable::FreezeAuthority(authority) => write!(f, "mint can be frozen by {}", authority),
+            Unsellable::RestrictiveExtensions(names) => write!(f, "mint has {}", names.join(", ")),
+            Unsellable::RoundTripFailed(e) => write!(f, "round trip quote failed: {}", e),
+            Unsellable::SimulationFailed(e) => write!(f, "buying and selling back failed in simulation: {}", e),
+            Unsellable::RoundTripLoss { loss_pct, max_pct } => {
+                write!(f, "round trip loses {:.2}% (max {:.2}%)", loss_pct, max_pct)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Unsellable {}
+
+/// Flags a mint can carry that block or claw back sells
+pub fn mint_red_flags(sellability: &SellabilityConfig, mint: &MintInfo) -> Option<Unsellable> {
+    if let (true, Some(authority)) = (sellability.reject_freeze_authority, mint.freeze_authority) {
+        return Some(Unsellable::FreezeAuthority(authority));
+    }
+    let restrictive = mint.restrictive_extensions();
+    (!restrictive.is_empty()).then_some(Unsellable::RestrictiveExtensions(restrictive))
+}
+
+/// Percent of the SOL put in that doesn't come back out
+pub fn round_trip_loss_pct(lamports_in: u64, lamports_out: u64) -> f64 {
+    if lamports_in == 0 {
+        return 0.0;
+    }
+    (1.0 - lamports_out as f64 / lamports_in as f64) * 100.0
+}
+
+/// Compute units for the simulated round trip, the most a transaction may ask for
+const SIMULATION_COMPUTE_UNITS: u32 = 1_400_000;
+
+fn quoted_amount(quote: &serde_json::Value, field: &str) -> Result<u64, Unsellable> {
+    quote[field]
+        .as_str()
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| Unsellable::RoundTripFailed(format!("missing {}", field)))
+}
+
+fn out_amount(quote: &serde_json::Value) -> Result<u64, Unsellable> {
+    quoted_amount(quote, "outAmount")
+}
+
+/// The buy leg followed by the sell leg, each as Jupiter would run it, under one compute budget
+pub fn round_trip_instructions(buy: &crate::swap::SwapInstructions, sell: &crate::swap::SwapInstructions) -> Vec<Instruction> {
+    let mut ixs = vec![ComputeBudgetInstruction::set_compute_unit_limit(SIMULATION_COMPUTE_UNITS)];
+    for leg in [buy, sell] {
+        ixs.extend(leg.setup.iter().cloned());
+        ixs.push(leg.swap.clone());
+        ixs.extend(leg.cleanup.iter().cloned());
+        ixs.extend(leg.other.iter().cloned());
+    }
+    ixs
+}
+
+/// Simulate buying and selling straight back in one transaction from `payer`.
+/// The sell runs against the tokens the buy just delivered, so a token that blocks sells
+/// (a hook, a frozen account, a program that only lets some wallets out) fails it.
+async fn simulate_round_trip(
+    cfg: &Config,
+    rpc: &RpcClient,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    buy_quote: &serde_json::Value,
+    sell_quote: &serde_json::Value,
+) -> Result<(), Unsellable> {
+    let failed = |e: anyhow::Error| Unsellable::SimulationFailed(e.to_string());
+    let buy = crate::swap::get_swap_instructions(cfg, payer, buy_quote).await.map_err(failed)?;
+    let sell = crate::swap::get_swap_instructions(cfg, payer, sell_quote).await.map_err(failed)?;
+    let mut tables = buy.address_lookup_tables.clone();
+    tables.extend(sell.address_lookup_tables.iter().filter(|key| !buy.address_lookup_tables.contains(key)));
+    let tables = crate::swap::load_lookup_tables(rpc, &tables).await.map_err(failed)?;
+    let message = v0::Message::try_compile(payer, &round_trip_instructions(&buy, &sell), &tables, Hash::default())
+        .map_err(|e| failed(e.into()))?;
+    let tx = VersionedTransaction { signatures: vec![Signature::default()], message: VersionedMessage::V0(message) };
+
+    // Long routes can't both fit in one transaction; the quotes and mint flags still stand
+    if !bincode::serialized_size(&tx).is_ok_and(|size| size as usize <= PACKET_DATA_SIZE) {
+        tracing::warn!("Round trip for {} is too large to simulate in one transaction, skipping the simulation", mint);
+        return Ok(());
+    }
+
+    let config = RpcSimulateTransactionConfig { sig_verify: false, replace_recent_blockhash: true, ..Default::default() };
+    let result = rpc.simulate_transaction_with_config(&tx, config).await.map_err(|e| failed(e.into()))?.value;
+    match result.err {
+        Some(err) => {
+            let last_log = result.logs.and_then(|logs| logs.last().cloned()).unwrap_or_default();
+            Err(Unsellable::SimulationFailed(format!("{} {}", err, last_log).trim_end().to_string()))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Quote a buy of `lamports` and the sell back of what it returns, then simulate both from `payer`.
+/// Returns the round-trip loss in percent when the token looks sellable.
+pub async fn check(
+    cfg: &Config,
+    rpc: &RpcClient,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    mint_info: Option<&MintInfo>,
+    lamports: u64,
+) -> Result<f64, Unsellable> {
+    let sellability = &cfg.sellability;
+    if let Some(flag) = mint_info.and_then(|info| mint_red_flags(sellability, info)) {
+        return Err(flag);
+    }
+
+    let sol_mint = Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap();
+    let buy_quote = crate::swap::get_quote(cfg, sol_mint, *mint, lamports, cfg.slippage_bps)
+        .await
+        .map_err(|e| Unsellable::RoundTripFailed(e.to_string()))?;
+    let mut tokens = out_amount(&buy_quote)?;
+    // The simulated sell can only spend what the buy is sure to deliver
+    let mut held = quoted_amount(&buy_quote, "otherAmountThreshold")?;
+
+    // Token-2022 fees are withheld on the way in and again on the way out
+    if let Some(info) = mint_info.filter(|info| info.transfer_fee.is_some()) {
+        let epoch = rpc.get_epoch_info().await.map_err(|e| Unsellable::RoundTripFailed(e.to_string()))?.epoch;
+        held = held.saturating_sub(info.transfer_fee(epoch, held));
+        tokens = tokens.saturating_sub(info.transfer_fee(epoch, tokens));
+        tokens = tokens.saturating_sub(info.transfer_fee(epoch, tokens));
+    }
+
+    let sell_quote = crate::swap::get_quote(cfg, *mint, sol_mint, tokens, cfg.slippage_bps)
+        .await
+        .map_err(|e| Unsellable::RoundTripFailed(e.to_string()))?;
+    if sell_quote.get("routePlan").and_then(|v| v.as_array()).is_none_or(|arr| arr.is_empty()) {
+        return Err(Unsellable::RoundTripFailed("no route back to SOL".to_string()));
+    }
+    let loss_pct = round_trip_loss_pct(lamports, out_amount(&sell_quote)?);
+    tracing::info!("🔁 Round trip for {}: {:.2}% loss", mint, loss_pct);
+    if loss_pct > sellability.max_round_trip_loss_pct {
+        return Err(Unsellable::RoundTripLoss { loss_pct, max_pct: sellability.max_round_trip_loss_pct });
+    }
+
+    let simulated_sell = crate::swap::get_quote(cfg, *mint, sol_mint, held, cfg.slippage_bps)
+        .await
+        .map_err(|e| Unsellable::RoundTripFailed(e.to_string()))?;
+    simulate_round_trip(cfg, rpc, payer, mint, &buy_quote, &simulated_sell).await?;
+
+    Ok(loss_pct)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn mint(freeze_authority: Option<Pubkey>, extension_types: Vec<u16>) -> MintInfo {
+        MintInfo { program: crate::token::TOKEN_2022_PROGRAM, decimals: 6, supply: 0, transfer_fee: None, freeze_authority, extension_types }
+    }
+
+    #[test]
+    fn test_mint_red_flags() {
+        let cfg = SellabilityConfig::default();
+        assert_eq!(mint_red_flags(&cfg, &mint(None, vec![1, 18])), None);
+
+        let freezer = Pubkey::new_unique();
+        assert_eq!(mint_red_flags(&cfg, &mint(Some(freezer), vec![])), Some(Unsellable::FreezeAuthority(freezer)));
+        let lenient = SellabilityConfig { reject_freeze_authority: false, ..Default::default() };
+        assert_eq!(mint_red_flags(&lenient, &mint(Some(freezer), vec![])), None);
+
+        assert_eq!(
+            mint_red_flags(&cfg, &mint(None, vec![12, 14])),
+            Some(Unsellable::RestrictiveExtensions(vec!["PermanentDelegate", "TransferHook"]))
+        );
+    }
+
+    fn swap_instructions(swap_program: Pubkey) -> crate::swap::SwapInstructions {
+        let ix = |program_id| Instruction { program_id, accounts: vec![], data: vec![] };
+        crate::swap::SwapInstructions {
+            compute_budget: vec![ComputeBudgetInstruction::set_compute_unit_limit(200_000)],
+            setup: vec![ix(Pubkey::new_unique())],
+            swap: ix(swap_program),
+            cleanup: Some(ix(Pubkey::new_unique())),
+            other: vec![],
+            address_lookup_tables: vec![],
+        }
+    }
+
+    #[test]
+    fn test_round_trip_runs_the_sell_after_the_buy() {
+        let (buy_amm, sell_amm) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let ixs = round_trip_instructions(&swap_instructions(buy_amm), &swap_instructions(sell_amm));
+        assert_eq!(ixs.len(), 7);
+        assert_eq!(ixs[0], ComputeBudgetInstruction::set_compute_unit_limit(SIMULATION_COMPUTE_UNITS));
+        assert_eq!((ixs[2].program_id, ixs[5].program_id), (buy_amm, sell_amm));
+    }
+
+    #[tokio::test]
+    async fn test_failed_sell_simulation_is_unsellable() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/quote"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "inAmount": "1000000",
+                "outAmount": "950000",
+                "otherAmountThreshold": "900000",
+                "priceImpactPct": "0.01",
+                "routePlan": [{ "swapInfo": { "label": "Pump.fun" }, "percent": 100 }],
+            })))
+            .mount(&server)
+            .await;
+        let program = Pubkey::new_unique().to_string();
+        Mock::given(method("POST"))
+            .and(path("/swap-instructions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "swapInstruction": { "programId": program, "accounts": [], "data": "" },
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "method": "simulateTransaction" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "context": { "slot": 1 },
+                    "value": {
+                        "err": { "InstructionError": [2, { "Custom": 6023 }] },
+                        "logs": ["Program log: sells are paused"],
+                        "accounts": null,
+                        "unitsConsumed": 1_000,
+                    },
+                },
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut cfg = crate::test_config();
+        cfg.jupiter.base_url = server.uri();
+        let rpc = RpcClient::new(server.uri());
+        let err = check(&cfg, &rpc, &Pubkey::new_unique(), &Pubkey::new_unique(), None, 1_000_000).await.unwrap_err();
+        match err {
+            Unsellable::SimulationFailed(reason) => assert!(reason.contains("sells are paused"), "{}", reason),
+            other => panic!("expected a failed simulation, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_round_trip_loss() {
+        assert!((round_trip_loss_pct(1_000_000, 900_000) - 10.0).abs() < 1e-9);
+        assert_eq!(round_trip_loss_pct(1_000_000, 0), 100.0);
+        assert_eq!(round_trip_loss_pct(0, 0), 0.0);
+    }
+}
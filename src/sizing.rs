@@ -0,0 +1,188 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use crate::profit_db::ProfitDatabase;
+use crate::Config;
+
+/// How the SOL amount for a buy is chosen
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SizingStrategy {
+    /// Always `amount_sol`
+    #[default]
+    Fixed,
+    /// A percentage of the current wallet balance
+    PercentOfBalance,
+    /// `amount_sol` scaled by target / realized volatility of recent trade returns
+    VolatilityScaled,
+    /// `amount_sol` scaled by the signal source's historical win rate
+    CallerWeighted,
+}
+
+/// Cap on a buy, as a multiple of `amount_sol`, when `max_per_trade_sol` isn't set
+pub const DEFAULT_MAX_PER_TRADE_MULTIPLE: f64 = 3.0;
+
+/// Position sizing settings (`[sizing]` table in config.toml)
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct SizingConfig {
+    pub strategy: SizingStrategy,
+    pub percent_of_balance: f64,
+    /// Standard deviation of per-trade returns we size for (0.5 = 50%)
+    pub target_volatility: f64,
+    pub lookback_trades: u32,
+    /// Trades a source needs before its win rate counts
+    pub min_trades: u32,
+    pub min_multiplier: f64,
+    pub max_multiplier: f64,
+    /// Hard cap on any single buy; unset caps it at `DEFAULT_MAX_PER_TRADE_MULTIPLE` × `amount_sol`
+    pub max_per_trade_sol: Option<f64>,
+    /// Balance always kept back for fees
+    pub reserve_sol: f64,
+}
+
+impl Default for SizingConfig {
+    fn default() -> Self {
+        Self {
+            strategy: SizingStrategy::Fixed,
+            percent_of_balance: 5.0,
+            target_volatility: 0.5,
+            lookback_trades: 20,
+            min_trades: 5,
+            min_multiplier: 0.5,
+            max_multiplier: 2.0,
+            max_per_trade_sol: None,
+            reserve_sol: 0.05,
+        }
+    }
+}
+
+/// Historical data the sizing strategies draw on
+#[derive(Debug, Clone, Default)]
+pub struct SizingInputs {
+    pub balance_sol: f64,
+    /// Returns (pnl / cost) of recent closed trades
+    pub recent_returns: Vec<f64>,
+    /// (trades, wins) for the signal source
+    pub source_record: Option<(u32, u32)>,
+}
+
+fn std_dev(values: &[f64]) -> Option<f64> {
+    if values.len() < 2 {
+        return None;
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let var = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64;
+    Some(var.sqrt())
+}
+
+/// Compute the buy size in SOL, or the reason no buy fits
+pub fn compute_size(sizing: &SizingConfig, base_sol: f64, inputs: &SizingInputs) -> Result<f64> {
+    let raw = match sizing.strategy {
+        SizingStrategy::Fixed => base_sol,
+        SizingStrategy::PercentOfBalance => inputs.balance_sol * sizing.percent_of_balance / 100.0,
+        SizingStrategy::VolatilityScaled => {
+            match std_dev(&inputs.recent_returns) {
+                Some(vol) if vol > 0.0 && inputs.recent_returns.len() >= sizing.min_trades as usize => {
+                    let multiplier = (sizing.target_volatility / vol).clamp(sizing.min_multiplier, sizing.max_multiplier);
+                    base_sol * multiplier
+                }
+                _ => base_sol,
+            }
+        }
+        SizingStrategy::CallerWeighted => match inputs.source_record {
+            Some((trades, wins)) if trades >= sizing.min_trades && trades > 0 => {
+                let win_rate = wins as f64 / trades as f64;
+                base_sol * (sizing.min_multiplier + (sizing.max_multiplier - sizing.min_multiplier) * win_rate)
+            }
+            _ => base_sol,
+        },
+    };
+
+    let available = inputs.balance_sol - sizing.reserve_sol;
+    let cap = sizing.max_per_trade_sol.unwrap_or(base_sol * DEFAULT_MAX_PER_TRADE_MULTIPLE);
+    let size = raw.min(cap).min(available);
+    if size <= 0.0 {
+        return Err(anyhow!(
+            "Balance {:.4} SOL leaves nothing above the {:.4} SOL reserve",
+            inputs.balance_sol, sizing.reserve_sol
+        ));
+    }
+    Ok(size)
+}
+
+/// Size a buy for a signal from `source` using the configured strategy
+pub fn position_size(cfg: &Config, db: &ProfitDatabase, source: &str, balance_sol: f64) -> Result<f64> {
+    let sizing = &cfg.sizing;
+    let mut inputs = SizingInputs { balance_sol, ..Default::default() };
+    match sizing.strategy {
+        SizingStrategy::VolatilityScaled => {
+            inputs.recent_returns = db.recent_returns(cfg.is_paper(), sizing.lookback_trades)
+                .map_err(|e| anyhow!("Failed to load recent returns: {}", e))?;
+        }
+        SizingStrategy::CallerWeighted => {
+            inputs.source_record = Some(db.source_record(source, cfg.is_paper())
+                .map_err(|e| anyhow!("Failed to load record for {}: {}", source, e))?);
+        }
+        _ => {}
+    }
+    let size = compute_size(sizing, cfg.amount_sol, &inputs)?;
+    tracing::info!("📐 {:?} sizing for {}: {:.4} SOL", sizing.strategy, source, size);
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sizing(strategy: SizingStrategy) -> SizingConfig {
+        SizingConfig { strategy, max_per_trade_sol: Some(10.0), reserve_sol: 0.1, ..Default::default() }
+    }
+
+    #[test]
+    fn test_fixed_and_percent_of_balance() {
+        let inputs = SizingInputs { balance_sol: 4.0, ..Default::default() };
+        assert_eq!(compute_size(&sizing(SizingStrategy::Fixed), 0.2, &inputs).unwrap(), 0.2);
+        assert!((compute_size(&sizing(SizingStrategy::PercentOfBalance), 0.2, &inputs).unwrap() - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cap_and_reserve() {
+        let mut cfg = sizing(SizingStrategy::Fixed);
+        cfg.max_per_trade_sol = Some(0.5);
+        let inputs = SizingInputs { balance_sol: 10.0, ..Default::default() };
+        assert_eq!(compute_size(&cfg, 2.0, &inputs).unwrap(), 0.5);
+
+        // Only what sits above the reserve can be spent
+        let inputs = SizingInputs { balance_sol: 0.3, ..Default::default() };
+        assert!((compute_size(&cfg, 2.0, &inputs).unwrap() - 0.2).abs() < 1e-9);
+        let inputs = SizingInputs { balance_sol: 0.05, ..Default::default() };
+        assert!(compute_size(&cfg, 2.0, &inputs).is_err());
+
+        // Without an explicit cap, a big balance still can't size past a multiple of the base amount
+        let uncapped = SizingConfig { max_per_trade_sol: None, ..sizing(SizingStrategy::PercentOfBalance) };
+        let inputs = SizingInputs { balance_sol: 1_000.0, ..Default::default() };
+        assert!((compute_size(&uncapped, 0.2, &inputs).unwrap() - 0.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_volatility_scaled() {
+        let cfg = SizingConfig { min_trades: 2, target_volatility: 0.5, ..sizing(SizingStrategy::VolatilityScaled) };
+        // Std dev of [1, -1] is ~1.41, so size shrinks toward the minimum multiplier
+        let inputs = SizingInputs { balance_sol: 10.0, recent_returns: vec![1.0, -1.0], ..Default::default() };
+        assert!((compute_size(&cfg, 1.0, &inputs).unwrap() - 0.5).abs() < 1e-9);
+        // Not enough history: base size
+        let inputs = SizingInputs { balance_sol: 10.0, recent_returns: vec![0.1], ..Default::default() };
+        assert_eq!(compute_size(&cfg, 1.0, &inputs).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_caller_weighted() {
+        let cfg = sizing(SizingStrategy::CallerWeighted);
+        let good = SizingInputs { balance_sol: 10.0, source_record: Some((10, 10)), ..Default::default() };
+        let bad = SizingInputs { balance_sol: 10.0, source_record: Some((10, 0)), ..Default::default() };
+        let new = SizingInputs { balance_sol: 10.0, source_record: Some((2, 2)), ..Default::default() };
+        assert_eq!(compute_size(&cfg, 1.0, &good).unwrap(), 2.0);
+        assert_eq!(compute_size(&cfg, 1.0, &bad).unwrap(), 0.5);
+        assert_eq!(compute_size(&cfg, 1.0, &new).unwrap(), 1.0);
+    }
+}
@@ -1,89 +1,431 @@
 use anyhow::Result;
-use anyhow::anyhow;
-use solana_sdk::pubkey::Pubkey;
-use std::sync::Arc;
-use solana_sdk::signer::keypair::Keypair;
-use yellowstone_grpc_client::GeyserGrpcClient;
-use yellowstone_grpc_proto::prelude::{SubscribeRequest, SubscribeRequestFilterAccounts};
-use futures_util::stream::TryStreamExt;
-use std::collections::HashMap;
-use std::time::Duration;
-use tokio::time::timeout;
-use tracing::{error, info};
-use bincode;
 use serde::Deserialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
 use crate::Config;
+use crate::price_feed::{PriceFeed, QuoteFeed};
+use crate::sell::{SellAmount, SellOutcome};
+use crate::profit_db::{PositionRecord, ProfitDatabase, TradeRecord, POSITION_CLOSED, POSITION_OPEN};
+use crate::wallets::Wallet;
+use crate::fills::Fill;
+
+/// A bought position handed to the exit manager, with the exit state that survives restarts
+#[derive(Debug, Clone)]
+pub struct Position {
+    pub mint: Pubkey,
+    /// Where the buy signal came from, for per-source stats
+    pub source: String,
+    pub cost_sol: f64,
+    /// Parsed buy fill, when the confirmed transaction could be read
+    pub fill: Option<Fill>,
+    /// Row in the positions table, once stored
+    pub id: Option<i64>,
+    /// Cost not yet closed out by partial exits
+    pub remaining_cost: f64,
+    pub entry_price: Option<f64>,
+    pub stop_price: Option<f64>,
+    /// Highest price seen since entry
+    pub peak_price: Option<f64>,
+    pub hit_breakeven: bool,
+    pub took_partial_profit: bool,
+}
+
+impl Position {
+    pub fn new(mint: Pubkey, source: String, cost_sol: f64, fill: Option<Fill>) -> Self {
+        Self {
+            mint,
+            source,
+            cost_sol,
+            fill,
+            id: None,
+            remaining_cost: cost_sol,
+            entry_price: None,
+            stop_price: None,
+            peak_price: None,
+            hit_breakeven: false,
+            took_partial_profit: false,
+        }
+    }
+
+    pub fn from_record(record: &PositionRecord) -> Result<Self> {
+        Ok(Self {
+            mint: record.mint.parse()?,
+            source: record.source.clone(),
+            cost_sol: record.cost_sol,
+            fill: record.fill,
+            id: Some(record.id),
+            remaining_cost: record.remaining_cost,
+            entry_price: record.entry_price,
+            stop_price: record.stop_price,
+            peak_price: record.peak_price,
+            hit_breakeven: record.hit_breakeven,
+            took_partial_profit: record.took_partial_profit,
+        })
+    }
 
-#[derive(Deserialize, Debug)]
-struct BondingCurve {
-    virtual_sol_reserves: u64,
-    virtual_token_reserves: u64,
-    // Other fields as needed
+    pub fn record(&self, wallet: &str, paper: bool, status: &str) -> PositionRecord {
+        PositionRecord {
+            id: self.id.unwrap_or_default(),
+            wallet: wallet.to_string(),
+            mint: self.mint.to_string(),
+            source: self.source.clone(),
+            paper,
+            cost_sol: self.cost_sol,
+            remaining_cost: self.remaining_cost,
+            fill: self.fill,
+            entry_price: self.entry_price,
+            stop_price: self.stop_price,
+            peak_price: self.peak_price,
+            hit_breakeven: self.hit_breakeven,
+            took_partial_profit: self.took_partial_profit,
+            status: status.to_string(),
+        }
+    }
 }
 
-pub async fn manage(mint: Pubkey, cfg: Config, payer: Arc<Keypair>) -> Result<()> {
-    let bonk_program: Pubkey = "LanMV9sAd7wArD4vJFi2qDdfnVhFxYSUg6eADduJ3uj".parse()?;
-    let (bonding_curve, _) = Pubkey::find_program_address(&[b"bonding-curve", mint.as_ref()], &bonk_program);
-    let stop_loss_multiplier = 1.0 - (cfg.stop_loss_pct as f64 / 100.0);
-    let take_profit_multiplier = 1.0 + (cfg.take_profit_pct as f64 / 100.0);
-    let breakeven_trigger_multiplier = 1.5;
-    let mut entry_price: f64 = 0.0;
-    let mut sl: f64 = 0.0;
-    let mut initialized = false;
-    let mut hit_breakeven = false;
-    let mut client = GeyserGrpcClient::build_from_shared(cfg.grpc_addr.clone())?
-        .x_token(Some(cfg.grpc_x_token.clone()))?
-        .connect().await?;
-    let req = SubscribeRequest {
-        accounts: HashMap::from([(
-            "bonding".to_string(),
-            SubscribeRequestFilterAccounts {
-                account: vec![bonding_curve.to_string()],
-                ..Default::default()
-            },
-        )]),
-        ..Default::default()
+/// Write the position's exit state, inserting it the first time
+pub fn save_position(cfg: &Config, db: &ProfitDatabase, wallet: &Wallet, position: &mut Position, status: &str) {
+    let record = position.record(&wallet.name, cfg.is_paper(), status);
+    let saved = match position.id {
+        Some(_) => db.update_position(&record),
+        None => db.insert_position(&record).map(|id| position.id = Some(id)),
     };
-    let mut stream = client.subscribe_once(req).await?;
+    if let Err(e) = saved {
+        error!("Failed to save position {} ({}): {}", position.mint, wallet.name, e);
+    }
+}
+
+/// Once the peak reaches `at` times entry, the stop is locked at no less than `lock` times entry
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct TrailStep {
+    pub at: f64,
+    pub lock: f64,
+}
+
+/// Trailing stop that follows the position's high-water mark (`[trailing]` table in config.toml)
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct TrailingConfig {
+    /// Peak multiple of entry at which the stop starts trailing; 0 turns the percent trail off
+    pub activation_multiple: f64,
+    /// How far below the peak the stop follows, in percent
+    pub trail_pct: f64,
+    /// Step schedule, applied alongside the percent trail; the higher stop wins
+    pub steps: Vec<TrailStep>,
+}
+
+impl Default for TrailingConfig {
+    fn default() -> Self {
+        Self { activation_multiple: 0.0, trail_pct: 25.0, steps: Vec::new() }
+    }
+}
+
+impl TrailingConfig {
+    /// Stop implied by the peak, once the trail or a step has kicked in
+    pub fn stop(&self, entry: f64, peak: f64) -> Option<f64> {
+        let ratio = peak / entry;
+        let trail = (self.activation_multiple > 0.0 && ratio >= self.activation_multiple)
+            .then(|| peak * (1.0 - self.trail_pct.clamp(0.0, 100.0) / 100.0));
+        self.steps
+            .iter()
+            .filter(|step| ratio >= step.at)
+            .map(|step| entry * step.lock)
+            .chain(trail)
+            .reduce(f64::max)
+    }
+}
+
+/// Why a stop at `stop` was hit at `price`, for logs and notifications
+pub fn stop_reason(trailing: &TrailingConfig, entry: f64, peak: Option<f64>, stop: f64, price: f64) -> String {
+    if let Some(peak) = peak {
+        if trailing.stop(entry, peak).is_some_and(|trail| trail >= stop) {
+            return format!(
+                "trail hit at {:.2}x from peak {:.2}x ({:.1}% off the high)",
+                price / entry,
+                peak / entry,
+                (1.0 - price / peak) * 100.0
+            );
+        }
+    }
+    if stop >= entry {
+        return format!("breakeven stop hit at {:.2}x", price / entry);
+    }
+    format!("stop loss hit at {:.2}x ({:+.1}% from entry)", price / entry, (price / entry - 1.0) * 100.0)
+}
+
+/// Book realized PnL for a sell against the share of cost it closed out
+async fn record_exit(cfg: &Config, db: &ProfitDatabase, wallet: &Wallet, position: &Position, outcome: &SellOutcome, cost_share: f64, reason: &str) {
+    if outcome.tokens_sold == 0 {
+        return;
+    }
+    let mint = &position.mint;
+    let pnl = outcome.sol_received - cost_share;
+    if outcome.transfer_fee > 0 {
+        info!("{} transfer fee withheld {} tokens on exit", mint, outcome.transfer_fee);
+    }
+    // The quote already allows for the transfer fee, so landing well under it means the fee or route changed
+    if let Some(expected) = outcome.expected_sol {
+        if outcome.sol_received < expected * (1.0 - cfg.slippage_bps as f64 / 10_000.0) {
+            warn!("Exit of {} returned {:.6} SOL, below the {:.6} SOL quoted after its transfer fee", mint, outcome.sol_received, expected);
+        }
+    }
+    let trade = TradeRecord {
+        wallet: &wallet.name,
+        mint: &mint.to_string(),
+        source: &position.source,
+        cost_sol: cost_share,
+        pnl,
+        paper: cfg.is_paper(),
+        position_id: position.id,
+    };
+    if let Err(e) = db.record_trade(&trade) {
+        error!("Failed to record profit for {}: {}", mint, e);
+    }
+    let tag = if cfg.is_paper() { format!("{} ", crate::paper::TAG) } else { String::new() };
+    crate::notifier::log(format!("{}📈 Realized PnL on {} ({}), {}: {:+.4} SOL", tag, mint, wallet.name, reason, pnl)).await;
+}
+
+/// How often a manager checks whether its position was closed by something else
+const CLOSED_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+const MANAGE_RETRY_START: Duration = Duration::from_secs(1);
+const MANAGE_RETRY_MAX: Duration = Duration::from_secs(60);
+
+/// Applies the exit rules to prices from whichever feed is tracking the position
+struct Manager {
+    position: Position,
+    cfg: Config,
+    wallet: Wallet,
+    db: ProfitDatabase,
+}
+
+impl Manager {
+    fn save(&mut self, status: &str) {
+        save_position(&self.cfg, &self.db, &self.wallet, &mut self.position, status);
+    }
+
+    async fn sell(&self, amount: SellAmount, cost_share: f64, reason: &str) {
+        info!("Selling {} of {}: {}", amount, self.position.mint, reason);
+        let outcome = crate::exit::sell_until_filled(self.position.mint, amount, &self.cfg, &self.wallet, &self.db).await;
+        record_exit(&self.cfg, &self.db, &self.wallet, &self.position, &outcome, cost_share, reason).await;
+    }
+
+    fn closed_elsewhere(&self) -> bool {
+        let Some(id) = self.position.id else {
+            return false;
+        };
+        match self.db.position_status(id) {
+            Ok(status) => status.as_deref() != Some(POSITION_OPEN),
+            Err(e) => {
+                warn!("Could not check the status of position {}: {}", id, e);
+                false
+            }
+        }
+    }
+
+    /// Fix the price the exit levels are measured from
+    fn set_entry(&mut self, price: f64) {
+        self.position.entry_price = Some(price);
+        self.position.stop_price = Some(price * (1.0 - self.cfg.stop_loss_pct as f64 / 100.0));
+        self.position.peak_price = Some(price);
+        self.save(POSITION_OPEN);
+    }
+
+    /// Act on a new price; true once the position is closed
+    async fn on_price(&mut self, price: f64) -> bool {
+        let stop_loss_multiplier = 1.0 - (self.cfg.stop_loss_pct as f64 / 100.0);
+        let take_profit_multiplier = 1.0 + (self.cfg.take_profit_pct as f64 / 100.0);
+        let breakeven_trigger_multiplier = 1.5;
+        let Some(entry_price) = self.position.entry_price else {
+            self.set_entry(price);
+            return false;
+        };
+        let mut sl = self.position.stop_price.unwrap_or(entry_price * stop_loss_multiplier);
+        // Ratchet the stop up behind a new high; it never moves down
+        if self.position.peak_price.is_none_or(|peak| price > peak) {
+            self.position.peak_price = Some(price);
+            if let Some(trail) = self.cfg.trailing.stop(entry_price, price).filter(|&trail| trail > sl) {
+                info!("Trailing stop on {} raised to {:.2}x with the peak at {:.2}x", self.position.mint, trail / entry_price, price / entry_price);
+                sl = trail;
+                self.position.stop_price = Some(trail);
+            }
+            self.save(POSITION_OPEN);
+        }
+        let ratio = price / entry_price;
+        if ratio >= take_profit_multiplier && !self.position.took_partial_profit {
+            let sell_pct = self.cfg.take_profit_sell_pct;
+            if sell_pct < 100 {
+                // Scale out and let the rest ride with the stop at entry
+                info!("Take profit triggered at {}x, selling {}%", take_profit_multiplier, sell_pct);
+                let cost_share = self.position.remaining_cost * sell_pct as f64 / 100.0;
+                self.sell(SellAmount::Percent(sell_pct), cost_share, &format!("take profit at {:.2}x", ratio)).await;
+                self.position.remaining_cost -= cost_share;
+                self.position.took_partial_profit = true;
+                self.position.stop_price = Some(sl.max(entry_price));
+                self.position.hit_breakeven = true;
+                self.save(POSITION_OPEN);
+                return false;
+            }
+            info!("Take profit triggered at {}x", take_profit_multiplier);
+            self.sell(SellAmount::All, self.position.remaining_cost, &format!("take profit at {:.2}x", ratio)).await;
+            self.save(POSITION_CLOSED);
+            return true;
+        }
+        if self.cfg.trail_up_50_sl && !self.position.hit_breakeven && ratio >= breakeven_trigger_multiplier {
+            self.position.stop_price = Some(sl.max(entry_price));
+            self.position.hit_breakeven = true;
+            info!("Breakeven SL set at entry price after hitting 1.5x");
+            self.save(POSITION_OPEN);
+        }
+        let stop = self.position.stop_price.unwrap_or(sl);
+        if price <= stop {
+            let reason = stop_reason(&self.cfg.trailing, entry_price, self.position.peak_price, stop, price);
+            self.sell(SellAmount::All, self.position.remaining_cost, &reason).await;
+            self.save(POSITION_CLOSED);
+            return true;
+        }
+        false
+    }
+}
+
+/// Retry a lookup the manager can't start without, backing off up to `MANAGE_RETRY_MAX`
+async fn until_ok<T, F, Fut>(what: &str, mint: &Pubkey, mut lookup: F) -> T
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut delay = MANAGE_RETRY_START;
+    loop {
+        match lookup().await {
+            Ok(value) => return value,
+            Err(e) => {
+                warn!("Could not {} for {}: {}; retrying in {}s", what, mint, e, delay.as_secs());
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MANAGE_RETRY_MAX);
+            }
+        }
+    }
+}
+
+/// Manage the position until it's sold or closed elsewhere; RPC trouble is retried rather than
+/// abandoning an open position
+pub async fn manage(mut position: Position, cfg: Config, wallet: Wallet, db: ProfitDatabase) {
+    let mint = position.mint;
+    if position.id.is_none() {
+        save_position(&cfg, &db, &wallet, &mut position, POSITION_OPEN);
+    }
+    if let Some(fill) = &position.fill {
+        info!("Managing {}: filled {} tokens at {:.3} lamports per token", mint, fill.tokens, fill.price());
+    }
+    let rpc = RpcClient::new(cfg.rpc_http.clone());
+    let decimals = until_ok("fetch the mint", &mint, || crate::token::fetch_mint(&rpc, &mint)).await.decimals;
+    let mut feed: Box<dyn PriceFeed> = match until_ok("look up the curve", &mint, || crate::curve::lookup_curve(&rpc, &mint)).await {
+        Some((launchpad, bonding_curve)) => match crate::price_feed::curve_feed(&cfg, launchpad, bonding_curve, mint, decimals) {
+            Ok(feed) => {
+                info!("Tracking {} on its {} curve {}", mint, launchpad, bonding_curve);
+                Box::new(feed)
+            }
+            Err(e) => {
+                error!("{}, tracking {} via Jupiter quotes", e, mint);
+                Box::new(QuoteFeed::new(&cfg, mint, decimals))
+            }
+        },
+        None => {
+            warn!("No pump.fun or LaunchLab curve found for {}, tracking via Jupiter quotes", mint);
+            Box::new(QuoteFeed::new(&cfg, mint, decimals))
+        }
+    };
+    let mut manager = Manager { position, cfg, wallet, db };
+    // Measure exits from what was actually paid; without a fill the first feed price stands in
+    if manager.position.entry_price.is_none() {
+        if let Some(price) = manager.position.fill.and_then(|fill| fill.entry_price(decimals)) {
+            info!("Entry price of {} from its fill: {:.3e} SOL per token", mint, price);
+            manager.set_entry(price);
+        }
+    }
+    let mut status_checked = Instant::now();
     loop {
-        match timeout(Duration::from_secs(30), stream.try_next()).await {
-            Ok(Ok(Some(update))) => {
-                if let Some(account_update) = update.update_oneof {
-                    if let yellowstone_grpc_proto::prelude::subscribe_update::UpdateOneof::Account(acc) = account_update {
-                        let data = acc.account.ok_or(anyhow!("No account in update"))?.data.clone();
-                        if let Ok(curve) = bincode::deserialize::<BondingCurve>(&data) {
-                            let price = curve.virtual_sol_reserves as f64 / curve.virtual_token_reserves as f64;
-                            if !initialized {
-                                entry_price = price;
-                                sl = entry_price * stop_loss_multiplier;
-                                initialized = true;
-                                continue;
-                            }
-                            let ratio = price / entry_price;
-                            if ratio >= take_profit_multiplier {
-                                info!("Take profit triggered at {}x", take_profit_multiplier);
-                                crate::sell::execute(mint, cfg.clone(), payer.clone()).await?;
-                                break;
-                            }
-                            if cfg.trail_up_50_sl && !hit_breakeven && ratio >= breakeven_trigger_multiplier {
-                                sl = entry_price;
-                                hit_breakeven = true;
-                                info!("Breakeven SL set at entry price after hitting 1.5x");
-                            }
-                            // No further trailing after breakeven
-                            if price <= sl {
-                                info!("Stop loss triggered at price {}", price);
-                                crate::sell::execute(mint, cfg.clone(), payer.clone()).await?;
-                                break;
-                            }
-                        }
-                    }
-                }
+        // A sell-all closes positions behind the manager's back
+        if status_checked.elapsed() >= CLOSED_CHECK_INTERVAL {
+            status_checked = Instant::now();
+            if manager.closed_elsewhere() {
+                info!("Position in {} ({}) was closed elsewhere, no longer managing it", mint, manager.wallet.name);
+                return;
             }
-            Ok(Ok(None)) => { error!("Stream closed"); break; }
-            Ok(Err(e)) => { error!("Error: {}", e); break; }
-            Err(_) => { error!("Timeout"); break; }
         }
+        let tick = match feed.next().await {
+            Ok(tick) => tick,
+            Err(e) => {
+                warn!("Could not price {} via {}: {}", mint, feed.name(), e);
+                continue;
+            }
+        };
+        // A finished curve no longer prices the token; same entry price and exit state, just a different feed
+        if tick.complete {
+            let msg = format!("🎓 {} graduated from its curve, now tracking its pool price via Jupiter quotes", mint);
+            info!("{}", msg);
+            crate::notifier::log(msg).await;
+            feed = Box::new(QuoteFeed::new(&manager.cfg, mint, decimals));
+            continue;
+        }
+        if manager.on_price(tick.price).await {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_until_ok_retries_lookups() {
+        let mut calls = 0;
+        let value = until_ok("look up", &Pubkey::new_unique(), || {
+            calls += 1;
+            let result = if calls < 2 { Err(anyhow::anyhow!("rpc down")) } else { Ok(calls) };
+            async move { result }
+        })
+        .await;
+        assert_eq!(value, 2);
+    }
+
+    fn trailing(activation_multiple: f64, steps: &[(f64, f64)]) -> TrailingConfig {
+        TrailingConfig {
+            activation_multiple,
+            trail_pct: 25.0,
+            steps: steps.iter().map(|&(at, lock)| TrailStep { at, lock }).collect(),
+        }
+    }
+
+    #[test]
+    fn test_trailing_stop_levels() {
+        assert_eq!(TrailingConfig::default().stop(1.0, 10.0), None);
+        let trail = trailing(2.0, &[]);
+        assert_eq!(trail.stop(1.0, 1.9), None);
+        assert_eq!(trail.stop(1.0, 2.0), Some(1.5));
+        assert_eq!(trail.stop(2.0, 8.0), Some(6.0));
+
+        // At 2x lock 1.5x, at 3x lock 2.2x
+        let steps = trailing(0.0, &[(2.0, 1.5), (3.0, 2.2)]);
+        assert_eq!(steps.stop(1.0, 1.5), None);
+        assert_eq!(steps.stop(1.0, 2.5), Some(1.5));
+        assert_eq!(steps.stop(1.0, 3.5), Some(2.2));
+
+        // Both configured: the tighter stop wins
+        let both = trailing(2.0, &[(2.0, 1.5), (3.0, 2.2)]);
+        assert_eq!(both.stop(1.0, 3.0), Some(2.25));
+        assert_eq!(both.stop(1.0, 2.0), Some(1.5));
+    }
+
+    #[test]
+    fn test_stop_reason() {
+        let trail = trailing(2.0, &[]);
+        assert_eq!(stop_reason(&trail, 1.0, Some(4.0), 3.0, 2.9), "trail hit at 2.90x from peak 4.00x (27.5% off the high)");
+        assert_eq!(stop_reason(&trail, 1.0, Some(1.6), 1.0, 0.95), "breakeven stop hit at 0.95x");
+        assert_eq!(stop_reason(&trail, 1.0, Some(1.2), 0.35, 0.3), "stop loss hit at 0.30x (-70.0% from entry)");
+        assert_eq!(stop_reason(&trail, 1.0, None, 0.35, 0.3), "stop loss hit at 0.30x (-70.0% from entry)");
     }
-    Ok(())
 }
@@ -1,37 +1,203 @@
 use anyhow::{anyhow, Result};
-use reqwest;
+use serde::Deserialize;
 use serde_json::{json, Value};
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{pubkey::Pubkey, transaction::VersionedTransaction};
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::message::{v0, AddressLookupTableAccount, VersionedMessage};
+use solana_sdk::address_lookup_table::state::AddressLookupTable;
+use solana_sdk::signer::{keypair::Keypair, Signer};
 use base64::{self, engine::general_purpose};
 use base64::Engine;
-use bincode;
 use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Duration;
 use crate::Config;
 
-pub async fn get_swap_transaction(
+/// Jupiter API settings (`[jupiter]` table in config.toml)
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct JupiterConfig {
+    pub base_url: String,
+    pub api_key: Option<String>,
+    pub max_retries: u32,
+    pub retry_backoff_ms: u64,
+    pub only_direct_routes: bool,
+    pub restrict_intermediate_tokens: bool,
+    pub max_accounts: Option<u32>,
+    pub dexes: Vec<String>,
+    /// Compose our own v0 transaction from `/swap-instructions` instead of using `swapTransaction`
+    pub use_swap_instructions: bool,
+    /// Overrides Jupiter's simulated compute unit limit when composing
+    pub compute_unit_limit: Option<u32>,
+    /// Optional tip transfer appended to composed transactions
+    pub tip_account: Option<String>,
+    pub tip_lamports: u64,
+    pub memo: Option<String>,
+}
+
+impl Default for JupiterConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "https://quote-api.jup.ag/v6".to_string(),
+            api_key: None,
+            max_retries: 3,
+            retry_backoff_ms: 500,
+            only_direct_routes: false,
+            restrict_intermediate_tokens: false,
+            max_accounts: None,
+            dexes: Vec::new(),
+            use_swap_instructions: false,
+            compute_unit_limit: None,
+            tip_account: None,
+            tip_lamports: 0,
+            memo: None,
+        }
+    }
+}
+
+/// Shared HTTP client so every Jupiter call reuses the same keep-alive pool
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .tcp_keepalive(Duration::from_secs(30))
+            .pool_idle_timeout(Duration::from_secs(90))
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("failed to build HTTP client")
+    })
+}
+
+fn endpoint(jup: &JupiterConfig, path: &str) -> String {
+    format!("{}/{}", jup.base_url.trim_end_matches('/'), path)
+}
+
+fn with_api_key(req: reqwest::RequestBuilder, jup: &JupiterConfig) -> reqwest::RequestBuilder {
+    match &jup.api_key {
+        Some(key) if !key.is_empty() => req.header("x-api-key", key),
+        _ => req,
+    }
+}
+
+/// Send a Jupiter request, retrying with exponential backoff on 429, 5xx and transport errors
+async fn send_with_retry<F>(jup: &JupiterConfig, build: F) -> Result<Value>
+where
+    F: Fn() -> reqwest::RequestBuilder,
+{
+    let mut attempt: u32 = 0;
+    loop {
+        let backoff = Duration::from_millis(jup.retry_backoff_ms.saturating_mul(1 << attempt.min(10)));
+        match with_api_key(build(), jup).send().await {
+            Ok(response) => {
+                let status = response.status();
+                if status.is_success() {
+                    return Ok(response.json::<Value>().await?);
+                }
+                let retryable = status.as_u16() == 429 || status.is_server_error();
+                if !retryable || attempt >= jup.max_retries {
+                    let body = response.text().await.unwrap_or_default();
+                    return Err(anyhow!("Jupiter request failed with {}: {}", status, body));
+                }
+                // Honour Retry-After when Jupiter sends one
+                let wait = response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.parse::<u64>().ok())
+                    .map(Duration::from_secs)
+                    .unwrap_or(backoff);
+                tracing::warn!("Jupiter returned {}, retrying in {}ms (attempt {}/{})",
+                    status, wait.as_millis(), attempt + 1, jup.max_retries);
+                tokio::time::sleep(wait).await;
+            }
+            Err(e) => {
+                if attempt >= jup.max_retries {
+                    return Err(e.into());
+                }
+                tracing::warn!("Jupiter request error: {}, retrying in {}ms (attempt {}/{})",
+                    e, backoff.as_millis(), attempt + 1, jup.max_retries);
+                tokio::time::sleep(backoff).await;
+            }
+        }
+        attempt += 1;
+    }
+}
+
+/// Build the query string for a `/quote` request including the optional routing params
+fn quote_params(jup: &JupiterConfig, input_mint: Pubkey, output_mint: Pubkey, amount: u64, slippage_bps: u16) -> Vec<(&'static str, String)> {
+    let mut params = vec![
+        ("inputMint", input_mint.to_string()),
+        ("outputMint", output_mint.to_string()),
+        ("amount", amount.to_string()),
+        ("slippageBps", slippage_bps.to_string()),
+    ];
+    if jup.only_direct_routes {
+        params.push(("onlyDirectRoutes", "true".to_string()));
+    }
+    if jup.restrict_intermediate_tokens {
+        params.push(("restrictIntermediateTokens", "true".to_string()));
+    }
+    if let Some(max_accounts) = jup.max_accounts {
+        params.push(("maxAccounts", max_accounts.to_string()));
+    }
+    if !jup.dexes.is_empty() {
+        params.push(("dexes", jup.dexes.join(",")));
+    }
+    params
+}
+
+/// Request a quote from Jupiter
+pub async fn get_quote(
     cfg: &Config,
-    payer: &Pubkey,
     input_mint: Pubkey,
     output_mint: Pubkey,
     amount: u64,
-) -> Result<VersionedTransaction> {
-    let client = reqwest::Client::new();
-    let quote_url = format!(
-        "https://quote-api.jup.ag/v6/quote?inputMint={}&outputMint={}&amount={}&slippageBps={}",
-        input_mint, output_mint, amount, cfg.slippage_bps
-    );
-    
-    tracing::info!("Requesting quote from Jupiter: {}", quote_url);
-    let quote_response = client.get(quote_url).send().await?.error_for_status()?.json::<Value>().await?;
-    
+    slippage_bps: u16,
+) -> Result<Value> {
+    let jup = &cfg.jupiter;
+    let url = endpoint(jup, "quote");
+    let params = quote_params(jup, input_mint, output_mint, amount, slippage_bps);
+    tracing::info!("Requesting quote from Jupiter: {} {:?}", url, params);
+    send_with_retry(jup, || http_client().get(&url).query(&params)).await
+}
+
+/// Lamports probed by the liquidity check; its quote doubles as the reference price for guards
+pub const PROBE_LAMPORTS: u64 = 1_000_000;
+
+/// Fetch a quote and reject it if it has no route or trips one of the `[guards]`.
+/// Sells are only guarded when `apply_to_sells` is set so exits never get stuck.
+pub async fn guarded_quote(
+    cfg: &Config,
+    input_mint: Pubkey,
+    output_mint: Pubkey,
+    amount: u64,
+    reference_out: Option<u64>,
+) -> Result<Value> {
+    let quote_response = get_quote(cfg, input_mint, output_mint, amount, cfg.slippage_bps).await?;
+
     // Check if Jupiter found any routes (pools)
-    if quote_response.get("routePlan").and_then(|v| v.as_array()).map_or(true, |arr| arr.is_empty()) {
+    if quote_response.get("routePlan").and_then(|v| v.as_array()).is_none_or(|arr| arr.is_empty()) {
         return Err(anyhow!("No liquidity pools found for token {} -> {}", input_mint, output_mint));
     }
-    
-    tracing::info!("Jupiter found route with {} steps", 
+
+    tracing::info!("Jupiter found route with {} steps",
         quote_response["routePlan"].as_array().map_or(0, |arr| arr.len()));
-    
+
+    let is_sell = output_mint.to_string() == "So11111111111111111111111111111111111111112";
+    if !is_sell || cfg.guards.apply_to_sells {
+        crate::guards::check_quote(&cfg.guards, &quote_response, &input_mint, &output_mint, reference_out)?;
+    }
+    Ok(quote_response)
+}
+
+pub async fn get_swap_transaction(
+    cfg: &Config,
+    payer: &Pubkey,
+    quote_response: &Value,
+) -> Result<VersionedTransaction> {
     let expected_out = quote_response["outAmount"].as_str().unwrap_or("0");
     tracing::info!("Expected output amount: {}", expected_out);
     let swap_request = json!({
@@ -40,41 +206,293 @@ pub async fn get_swap_transaction(
         "wrapAndUnwrapSol": true,
         "computeUnitPriceMicroLamports": cfg.priority_fee_microlamports
     });
-    let swap_response = client.post("https://quote-api.jup.ag/v6/swap")
-        .json(&swap_request)
-        .send().await?.error_for_status()?.json::<Value>().await?;
+    let swap_url = endpoint(&cfg.jupiter, "swap");
+    let swap_response = send_with_retry(&cfg.jupiter, || http_client().post(&swap_url).json(&swap_request)).await?;
     let swap_tx_b64 = swap_response["swapTransaction"].as_str().ok_or(anyhow!("Missing swapTransaction"))?.to_string();
     let tx_bytes = general_purpose::STANDARD.decode(swap_tx_b64)?;
     let tx: VersionedTransaction = bincode::deserialize(&tx_bytes)?;
     Ok(tx)
 }
 
-/// Check if a token has sufficient liquidity for trading
-pub async fn check_token_liquidity(token_mint: &Pubkey, _min_liquidity_sol: f64) -> Result<bool> {
-    let client = reqwest::Client::new();
+const MEMO_PROGRAM: &str = "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr";
+
+/// Instructions returned by Jupiter's `/swap-instructions` endpoint
+#[derive(Debug, Clone)]
+pub struct SwapInstructions {
+    pub compute_budget: Vec<Instruction>,
+    pub setup: Vec<Instruction>,
+    pub swap: Instruction,
+    pub cleanup: Option<Instruction>,
+    pub other: Vec<Instruction>,
+    pub address_lookup_tables: Vec<Pubkey>,
+}
+
+/// Our own instructions added around the Jupiter swap when composing a transaction,
+/// and what the quote is checked against before it's built
+#[derive(Debug, Clone, Default)]
+pub struct ExtraInstructions {
+    /// Instructions that must run before anything else (e.g. `AdvanceNonce`)
+    pub prefix: Vec<Instruction>,
+    /// Token account to close after the swap, with its owning token program
+    pub close_token_account: Option<(Pubkey, Pubkey)>,
+    /// Nonce value signed in place of a recent blockhash
+    pub durable_nonce: Option<Hash>,
+    /// Output expected at the signal's price, for the shortfall guard
+    pub reference_out: Option<u64>,
+}
+
+impl ExtraInstructions {
+    /// Close the token account once the swap has emptied it
+    pub fn close(account: Pubkey, token_program: Pubkey) -> Self {
+        Self { close_token_account: Some((account, token_program)), ..Default::default() }
+    }
+
+    /// Sign against a durable nonce so the transaction doesn't expire with the blockhash
+    pub fn with_nonce(mut self, nonce_account: Pubkey, authority: Pubkey, nonce: Hash) -> Self {
+        self.prefix.insert(0, solana_system_interface::instruction::advance_nonce_account(&nonce_account, &authority));
+        self.durable_nonce = Some(nonce);
+        self
+    }
+}
+
+/// Decode a single `{programId, accounts, data}` instruction object from Jupiter
+fn parse_instruction(value: &Value) -> Result<Instruction> {
+    let program_id = Pubkey::from_str(value["programId"].as_str().ok_or(anyhow!("Missing programId"))?)?;
+    let accounts = value["accounts"]
+        .as_array()
+        .ok_or(anyhow!("Missing accounts"))?
+        .iter()
+        .map(|acc| {
+            let pubkey = Pubkey::from_str(acc["pubkey"].as_str().ok_or(anyhow!("Missing account pubkey"))?)?;
+            Ok(AccountMeta {
+                pubkey,
+                is_signer: acc["isSigner"].as_bool().unwrap_or(false),
+                is_writable: acc["isWritable"].as_bool().unwrap_or(false),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let data = general_purpose::STANDARD.decode(value["data"].as_str().ok_or(anyhow!("Missing instruction data"))?)?;
+    Ok(Instruction { program_id, accounts, data })
+}
+
+fn parse_instruction_list(value: &Value) -> Result<Vec<Instruction>> {
+    match value.as_array() {
+        Some(list) => list.iter().map(parse_instruction).collect(),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Parse the `/swap-instructions` response body
+pub fn parse_swap_instructions(response: &Value) -> Result<SwapInstructions> {
+    if let Some(err) = response.get("error").and_then(|v| v.as_str()) {
+        return Err(anyhow!("Jupiter swap-instructions error: {}", err));
+    }
+    let cleanup = match response.get("cleanupInstruction") {
+        Some(v) if !v.is_null() => Some(parse_instruction(v)?),
+        _ => None,
+    };
+    let address_lookup_tables = response["addressLookupTableAddresses"]
+        .as_array()
+        .map(|list| list.iter().filter_map(|v| v.as_str()).map(Pubkey::from_str).collect::<Result<Vec<_>, _>>())
+        .transpose()?
+        .unwrap_or_default();
+    Ok(SwapInstructions {
+        compute_budget: parse_instruction_list(&response["computeBudgetInstructions"])?,
+        setup: parse_instruction_list(&response["setupInstructions"])?,
+        swap: parse_instruction(&response["swapInstruction"])?,
+        cleanup,
+        other: parse_instruction_list(&response["otherInstructions"])?,
+        address_lookup_tables,
+    })
+}
+
+/// Fetch the swap instructions for a quote
+pub async fn get_swap_instructions(cfg: &Config, payer: &Pubkey, quote_response: &Value) -> Result<SwapInstructions> {
+    let request = json!({
+        "quoteResponse": quote_response,
+        "userPublicKey": payer.to_string(),
+        "wrapAndUnwrapSol": true,
+    });
+    let url = endpoint(&cfg.jupiter, "swap-instructions");
+    let response = send_with_retry(&cfg.jupiter, || http_client().post(&url).json(&request)).await?;
+    parse_swap_instructions(&response)
+}
+
+/// Order all instructions for a composed transaction: prefix, compute budget, setup, swap, cleanup, other, close, tip, memo
+pub fn assemble_instructions(
+    cfg: &Config,
+    payer: &Pubkey,
+    parts: &SwapInstructions,
+    extras: &ExtraInstructions,
+) -> Result<Vec<Instruction>> {
+    let compute_budget_program = solana_sdk::compute_budget::id();
+    let mut ixs = extras.prefix.clone();
+
+    // We always set our own unit price; Jupiter's simulated limit is kept unless overridden
+    match cfg.jupiter.compute_unit_limit {
+        Some(limit) => ixs.push(ComputeBudgetInstruction::set_compute_unit_limit(limit)),
+        None => ixs.extend(
+            parts.compute_budget.iter()
+                .filter(|ix| ix.program_id == compute_budget_program && ix.data.first() == Some(&2))
+                .cloned(),
+        ),
+    }
+    ixs.push(ComputeBudgetInstruction::set_compute_unit_price(cfg.priority_fee_microlamports));
+
+    ixs.extend(parts.setup.iter().cloned());
+    ixs.push(parts.swap.clone());
+    ixs.extend(parts.cleanup.iter().cloned());
+    ixs.extend(parts.other.iter().cloned());
+
+    if let Some((account, token_program)) = extras.close_token_account {
+        ixs.push(crate::token::close_account_instruction(&token_program, &account, payer, payer));
+    }
+
+    if let Some(tip_account) = &cfg.jupiter.tip_account {
+        if cfg.jupiter.tip_lamports > 0 {
+            let tip_account = Pubkey::from_str(tip_account)?;
+            ixs.push(solana_sdk::system_instruction::transfer(payer, &tip_account, cfg.jupiter.tip_lamports));
+        }
+    }
+
+    if let Some(memo) = &cfg.jupiter.memo {
+        ixs.push(Instruction {
+            program_id: Pubkey::from_str(MEMO_PROGRAM)?,
+            accounts: vec![],
+            data: memo.as_bytes().to_vec(),
+        });
+    }
+
+    Ok(ixs)
+}
+
+/// Load the address lookup tables Jupiter referenced
+pub async fn load_lookup_tables(rpc: &RpcClient, keys: &[Pubkey]) -> Result<Vec<AddressLookupTableAccount>> {
+    if keys.is_empty() {
+        return Ok(Vec::new());
+    }
+    let accounts = rpc.get_multiple_accounts(keys).await?;
+    keys.iter()
+        .zip(accounts)
+        .map(|(key, account)| {
+            let account = account.ok_or(anyhow!("Address lookup table {} not found", key))?;
+            let table = AddressLookupTable::deserialize(&account.data)
+                .map_err(|e| anyhow!("Bad address lookup table {}: {}", key, e))?;
+            Ok(AddressLookupTableAccount { key: *key, addresses: table.addresses.to_vec() })
+        })
+        .collect()
+}
+
+/// Compose and sign a v0 transaction from `/swap-instructions` plus our own instructions
+pub async fn compose_swap_transaction(
+    cfg: &Config,
+    rpc: &RpcClient,
+    payer: &Keypair,
+    quote_response: &Value,
+    extras: &ExtraInstructions,
+    blockhash: Hash,
+) -> Result<VersionedTransaction> {
+    let parts = get_swap_instructions(cfg, &payer.pubkey(), quote_response).await?;
+    let ixs = assemble_instructions(cfg, &payer.pubkey(), &parts, extras)?;
+    let lookup_tables = load_lookup_tables(rpc, &parts.address_lookup_tables).await?;
+    let message = v0::Message::try_compile(&payer.pubkey(), &ixs, &lookup_tables, blockhash)?;
+    Ok(VersionedTransaction::try_new(VersionedMessage::V0(message), &[payer])?)
+}
+
+/// Replace the blockhash of a prebuilt Jupiter transaction and sign it
+pub fn resign_with_blockhash(mut tx: VersionedTransaction, payer: &Keypair, blockhash: Hash) -> VersionedTransaction {
+    let mut message = tx.message.clone();
+    match &mut message {
+        VersionedMessage::Legacy(ref mut msg) => {
+            msg.recent_blockhash = blockhash;
+        }
+        VersionedMessage::V0(ref mut msg) => {
+            msg.recent_blockhash = blockhash;
+        }
+    }
+    let message_hash = message.hash();
+    let sig = payer.sign_message(message_hash.as_ref());
+    tx.message = message;
+    tx.signatures = vec![sig];
+    tx
+}
+
+/// Build a signed swap transaction, composing it ourselves when `use_swap_instructions` is set
+pub async fn build_swap_transaction(
+    cfg: &Config,
+    rpc: &RpcClient,
+    payer: &Keypair,
+    input_mint: Pubkey,
+    output_mint: Pubkey,
+    amount: u64,
+    extras: &ExtraInstructions,
+) -> Result<VersionedTransaction> {
+    if extras.durable_nonce.is_some() && !cfg.jupiter.use_swap_instructions {
+        return Err(anyhow!("Durable nonce transactions need jupiter.use_swap_instructions"));
+    }
+    let quote_response = guarded_quote(cfg, input_mint, output_mint, amount, extras.reference_out).await?;
+    if !cfg.jupiter.use_swap_instructions {
+        let tx = get_swap_transaction(cfg, &payer.pubkey(), &quote_response).await?;
+        let bh = rpc.get_latest_blockhash().await?;
+        return Ok(resign_with_blockhash(tx, payer, bh));
+    }
+
+    tracing::info!("Composing swap from Jupiter instructions, expected output: {}",
+        quote_response["outAmount"].as_str().unwrap_or("0"));
+    let bh = match extras.durable_nonce {
+        Some(nonce) => nonce,
+        None => rpc.get_latest_blockhash().await?,
+    };
+    compose_swap_transaction(cfg, rpc, payer, &quote_response, extras, bh).await
+}
+
+/// Build and sign a PumpPortal sell of `amount` (a UI token amount or a percentage like `100%`).
+/// PumpPortal routes through pump.fun, PumpSwap and Raydium, so it works when Jupiter has no route.
+pub async fn pumpportal_sell_transaction(
+    url: &str,
+    payer: &Keypair,
+    mint: &Pubkey,
+    amount: &str,
+    slippage_bps: u16,
+    priority_fee_sol: f64,
+) -> Result<VersionedTransaction> {
+    let body = json!({
+        "publicKey": payer.pubkey().to_string(),
+        "action": "sell",
+        "mint": mint.to_string(),
+        "amount": amount,
+        "denominatedInSol": "false",
+        "slippage": (slippage_bps as f64 / 100.0).ceil() as u64,
+        "priorityFee": priority_fee_sol,
+        "pool": "auto",
+    });
+    let response = http_client().post(url).json(&body).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(anyhow!("PumpPortal request failed with {}: {}", status, body));
+    }
+    let bytes = response.bytes().await?;
+    let tx: VersionedTransaction = bincode::deserialize(&bytes)?;
+    Ok(VersionedTransaction::try_new(tx.message, &[payer])?)
+}
+
+/// Check if a token has sufficient liquidity for trading.
+/// Returns the tokens quoted for `PROBE_LAMPORTS` when it does.
+pub async fn check_token_liquidity(cfg: &Config, token_mint: &Pubkey, _min_liquidity_sol: f64) -> Result<Option<u64>> {
     let sol_mint = Pubkey::from_str("So11111111111111111111111111111111111111112")?;
-    let amount_lamports = (0.001 * 1e9) as u64; // Test with 0.001 SOL
-    
-    let quote_url = format!(
-        "https://quote-api.jup.ag/v6/quote?inputMint={}&outputMint={}&amount={}&slippageBps=300",
-        sol_mint, token_mint, amount_lamports
-    );
-    
-    match client.get(quote_url).send().await {
-        Ok(response) => {
-            if response.status().is_success() {
-                if let Ok(quote_response) = response.json::<Value>().await {
-                    // Check if we have routes and reasonable output
-                    if let Some(route_plan) = quote_response.get("routePlan").and_then(|v| v.as_array()) {
-                        if !route_plan.is_empty() {
-                            if let Some(out_amount) = quote_response.get("outAmount").and_then(|v| v.as_str()) {
-                                if let Ok(output_tokens) = out_amount.parse::<u64>() {
-                                    // Check if we get reasonable output (indicates good liquidity)
-                                    tracing::info!("Token {} liquidity check: {} tokens for 0.001 SOL", 
-                                        token_mint, output_tokens);
-                                    return Ok(output_tokens > 0);
-                                }
-                            }
+
+    match get_quote(cfg, sol_mint, *token_mint, PROBE_LAMPORTS, 300).await {
+        Ok(quote_response) => {
+            // Check if we have routes and reasonable output
+            if let Some(route_plan) = quote_response.get("routePlan").and_then(|v| v.as_array()) {
+                if !route_plan.is_empty() {
+                    if let Some(out_amount) = quote_response.get("outAmount").and_then(|v| v.as_str()) {
+                        if let Ok(output_tokens) = out_amount.parse::<u64>() {
+                            // Check if we get reasonable output (indicates good liquidity)
+                            tracing::info!("Token {} liquidity check: {} tokens for 0.001 SOL",
+                                token_mint, output_tokens);
+                            return Ok((output_tokens > 0).then_some(output_tokens));
                         }
                     }
                 }
@@ -84,6 +502,229 @@ pub async fn check_token_liquidity(token_mint: &Pubkey, _min_liquidity_sol: f64)
             tracing::warn!("Failed to check liquidity for {}: {}", token_mint, e);
         }
     }
-    
-    Ok(false)
-} 
\ No newline at end of file
+
+    Ok(None)
+}
+
+/// Output expected for `amount` lamports at the probe quote's price, used as the guards' reference
+pub fn reference_output(probe_out: u64, amount: u64) -> u64 {
+    (probe_out as u128 * amount as u128 / PROBE_LAMPORTS as u128) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn mock_config(server: &MockServer) -> Config {
+        let mut cfg = crate::test_config();
+        cfg.jupiter.base_url = server.uri();
+        cfg.jupiter.retry_backoff_ms = 1;
+        cfg
+    }
+
+    fn sample_quote() -> Value {
+        json!({
+            "inAmount": "1000000",
+            "outAmount": "3500000",
+            "priceImpactPct": "0.01",
+            "routePlan": [{ "swapInfo": { "label": "Pump.fun" }, "percent": 100 }]
+        })
+    }
+
+    fn ix_json(program: &Pubkey, data: &[u8]) -> Value {
+        json!({
+            "programId": program.to_string(),
+            "accounts": [{ "pubkey": Pubkey::new_unique().to_string(), "isSigner": false, "isWritable": true }],
+            "data": general_purpose::STANDARD.encode(data),
+        })
+    }
+
+    #[test]
+    fn test_assemble_composed_instructions() {
+        let mut cfg = crate::test_config();
+        let tip_account = Pubkey::new_unique();
+        cfg.jupiter.tip_account = Some(tip_account.to_string());
+        cfg.jupiter.tip_lamports = 10_000;
+        cfg.jupiter.memo = Some("snipe".to_string());
+
+        let compute_budget = solana_sdk::compute_budget::id();
+        let amm = Pubkey::new_unique();
+        let response = json!({
+            "computeBudgetInstructions": [ix_json(&compute_budget, &[2, 0, 0, 4, 0]), ix_json(&compute_budget, &[3, 1, 0, 0, 0, 0, 0, 0, 0])],
+            "setupInstructions": [ix_json(&Pubkey::new_unique(), &[1])],
+            "swapInstruction": ix_json(&amm, &[7, 7]),
+            "cleanupInstruction": null,
+            "otherInstructions": [],
+            "addressLookupTableAddresses": [Pubkey::new_unique().to_string()],
+        });
+        let parts = parse_swap_instructions(&response).unwrap();
+        assert_eq!(parts.address_lookup_tables.len(), 1);
+        assert!(parts.cleanup.is_none());
+
+        let payer = Pubkey::new_unique();
+        let ata = Pubkey::new_unique();
+        let ixs = assemble_instructions(&cfg, &payer, &parts, &ExtraInstructions::close(ata, crate::token::TOKEN_PROGRAM)).unwrap();
+        let programs: Vec<Pubkey> = ixs.iter().map(|ix| ix.program_id).collect();
+
+        // Jupiter's unit limit kept, its unit price replaced by ours
+        assert_eq!(ixs[0].data[0], 2);
+        assert_eq!(ixs[1], ComputeBudgetInstruction::set_compute_unit_price(cfg.priority_fee_microlamports));
+        assert_eq!(programs[3], amm);
+        assert_eq!(ixs[4].data, vec![9]);
+        assert_eq!(ixs[4].accounts[0].pubkey, ata);
+        assert_eq!(ixs[5], solana_sdk::system_instruction::transfer(&payer, &tip_account, 10_000));
+        assert_eq!(programs[6], Pubkey::from_str(MEMO_PROGRAM).unwrap());
+        assert_eq!(ixs.len(), 7);
+    }
+
+    #[test]
+    fn test_compute_unit_limit_override() {
+        let mut cfg = crate::test_config();
+        cfg.jupiter.compute_unit_limit = Some(150_000);
+        let compute_budget = solana_sdk::compute_budget::id();
+        let response = json!({
+            "computeBudgetInstructions": [ix_json(&compute_budget, &[2, 0, 0, 4, 0])],
+            "swapInstruction": ix_json(&Pubkey::new_unique(), &[1]),
+        });
+        let parts = parse_swap_instructions(&response).unwrap();
+        let ixs = assemble_instructions(&cfg, &Pubkey::new_unique(), &parts, &ExtraInstructions::default()).unwrap();
+        assert_eq!(ixs[0], ComputeBudgetInstruction::set_compute_unit_limit(150_000));
+        assert_eq!(ixs.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_quote_retries_on_429() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/quote"))
+            .respond_with(ResponseTemplate::new(429))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/quote"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(sample_quote()))
+            .mount(&server)
+            .await;
+
+        let cfg = mock_config(&server);
+        let quote = get_quote(&cfg, Pubkey::new_unique(), Pubkey::new_unique(), 1_000_000, 500).await.unwrap();
+        assert_eq!(quote["outAmount"], "3500000");
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_quote_gives_up_after_max_retries() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/quote"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+
+        let mut cfg = mock_config(&server);
+        cfg.jupiter.max_retries = 2;
+        let result = get_quote(&cfg, Pubkey::new_unique(), Pubkey::new_unique(), 1_000_000, 500).await;
+        assert!(result.is_err());
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_quote_does_not_retry_client_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/quote"))
+            .respond_with(ResponseTemplate::new(400))
+            .mount(&server)
+            .await;
+
+        let cfg = mock_config(&server);
+        let result = get_quote(&cfg, Pubkey::new_unique(), Pubkey::new_unique(), 1_000_000, 500).await;
+        assert!(result.is_err());
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_quote_sends_api_key_and_route_params() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/quote"))
+            .and(header("x-api-key", "secret"))
+            .and(query_param("slippageBps", "250"))
+            .and(query_param("onlyDirectRoutes", "true"))
+            .and(query_param("restrictIntermediateTokens", "true"))
+            .and(query_param("maxAccounts", "40"))
+            .and(query_param("dexes", "Raydium,Pump.fun"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(sample_quote()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut cfg = mock_config(&server);
+        cfg.jupiter.api_key = Some("secret".to_string());
+        cfg.jupiter.only_direct_routes = true;
+        cfg.jupiter.restrict_intermediate_tokens = true;
+        cfg.jupiter.max_accounts = Some(40);
+        cfg.jupiter.dexes = vec!["Raydium".to_string(), "Pump.fun".to_string()];
+        get_quote(&cfg, Pubkey::new_unique(), Pubkey::new_unique(), 1_000_000, 250).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_liquidity_check_against_mock() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/quote"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "outAmount": "0", "routePlan": [] })))
+            .mount(&server)
+            .await;
+
+        let cfg = mock_config(&server);
+        assert!(check_token_liquidity(&cfg, &Pubkey::new_unique(), 0.1).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_guarded_quote_reports_tripped_guard() {
+        let server = MockServer::start().await;
+        let mut quote = sample_quote();
+        quote["priceImpactPct"] = json!("0.4");
+        Mock::given(method("GET"))
+            .and(path("/quote"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(quote))
+            .mount(&server)
+            .await;
+
+        let mut cfg = mock_config(&server);
+        cfg.guards.max_price_impact_pct = Some(10.0);
+        let sol_mint = Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap();
+        let err = guarded_quote(&cfg, sol_mint, Pubkey::new_unique(), 1_000_000, None).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(crate::guards::GuardViolation::PriceImpact { .. })));
+
+        // Sells are left alone unless asked for
+        assert!(guarded_quote(&cfg, Pubkey::new_unique(), sol_mint, 1_000_000, None).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_pumpportal_sell_is_signed() {
+        let server = MockServer::start().await;
+        let payer = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let message = v0::Message::try_compile(&payer.pubkey(), &[], &[], Hash::new_unique()).unwrap();
+        let unsigned = VersionedTransaction {
+            signatures: vec![solana_sdk::signature::Signature::default()],
+            message: VersionedMessage::V0(message),
+        };
+        Mock::given(method("POST"))
+            .and(path("/api/trade-local"))
+            .and(body_partial_json(json!({ "action": "sell", "mint": mint.to_string(), "amount": "100%", "slippage": 15 })))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(bincode::serialize(&unsigned).unwrap()))
+            .mount(&server)
+            .await;
+
+        let url = format!("{}/api/trade-local", server.uri());
+        let tx = pumpportal_sell_transaction(&url, &payer, &mint, "100%", 1_500, 0.0005).await.unwrap();
+        assert_eq!(tx.verify_with_results(), vec![true]);
+        assert_eq!(tx.message.static_account_keys()[0], payer.pubkey());
+    }
+}
@@ -0,0 +1,129 @@
+use anyhow::Result;
+use solana_account_decoder::UiAccountData;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_request::TokenAccountsFilter;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::signer::{keypair::Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+use std::str::FromStr;
+use tracing::{info, warn};
+use crate::token::{TOKEN_2022_PROGRAM, TOKEN_PROGRAM};
+
+/// CloseAccount instructions packed into one transaction
+const CLOSE_BATCH_SIZE: usize = 20;
+
+/// A zero-balance token account that can be closed for its rent
+#[derive(Debug, Clone)]
+pub struct EmptyAccount {
+    pub address: Pubkey,
+    pub program: Pubkey,
+    pub lamports: u64,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SweepReport {
+    pub closed: usize,
+    pub failed: usize,
+    pub reclaimed_lamports: u64,
+    pub signatures: Vec<Signature>,
+}
+
+impl SweepReport {
+    pub fn reclaimed_sol(&self) -> f64 {
+        self.reclaimed_lamports as f64 / 1e9
+    }
+}
+
+/// Whether a jsonParsed token account can be closed: zero balance and no withheld Token-2022 fees
+fn is_closable(info: &serde_json::Value) -> bool {
+    let amount = info["tokenAmount"]["amount"].as_str().unwrap_or("1");
+    let withheld = info["extensions"]
+        .as_array()
+        .map(|exts| {
+            exts.iter().any(|ext| {
+                ext["extension"] == "transferFeeAmount"
+                    && ext["state"]["withheldAmount"].as_u64().unwrap_or(0) > 0
+            })
+        })
+        .unwrap_or(false);
+    amount == "0" && !withheld
+}
+
+/// Scan the wallet's Token and Token-2022 accounts for ones with zero balance
+pub async fn find_empty_token_accounts(rpc: &RpcClient, owner: &Pubkey) -> Result<Vec<EmptyAccount>> {
+    let mut empty = Vec::new();
+    for program in [TOKEN_PROGRAM, TOKEN_2022_PROGRAM] {
+        let accounts = rpc.get_token_accounts_by_owner(owner, TokenAccountsFilter::ProgramId(program)).await?;
+        for keyed in accounts {
+            if let UiAccountData::Json(parsed) = &keyed.account.data {
+                if is_closable(&parsed.parsed["info"]) {
+                    empty.push(EmptyAccount {
+                        address: Pubkey::from_str(&keyed.pubkey)?,
+                        program,
+                        lamports: keyed.account.lamports,
+                    });
+                }
+            }
+        }
+    }
+    Ok(empty)
+}
+
+/// Close the given accounts in batched transactions, returning rent to the payer
+pub async fn close_accounts(rpc: &RpcClient, payer: &Keypair, accounts: &[EmptyAccount]) -> SweepReport {
+    let mut report = SweepReport::default();
+    for batch in accounts.chunks(CLOSE_BATCH_SIZE) {
+        let ixs: Vec<_> = batch
+            .iter()
+            .map(|acc| crate::token::close_account_instruction(&acc.program, &acc.address, &payer.pubkey(), &payer.pubkey()))
+            .collect();
+        let result = async {
+            let bh = rpc.get_latest_blockhash().await?;
+            let tx = Transaction::new_signed_with_payer(&ixs, Some(&payer.pubkey()), &[payer], bh);
+            Ok::<_, anyhow::Error>(rpc.send_and_confirm_transaction(&tx).await?)
+        }
+        .await;
+        match result {
+            Ok(signature) => {
+                report.closed += batch.len();
+                report.reclaimed_lamports += batch.iter().map(|acc| acc.lamports).sum::<u64>();
+                info!("🧹 Closed {} token accounts, TX: {}", batch.len(), signature);
+                report.signatures.push(signature);
+            }
+            Err(e) => {
+                report.failed += batch.len();
+                warn!("Failed to close batch of {} token accounts: {}", batch.len(), e);
+            }
+        }
+    }
+    report
+}
+
+/// Close every empty token account owned by the payer
+pub async fn sweep(rpc: &RpcClient, payer: &Keypair) -> Result<SweepReport> {
+    let empty = find_empty_token_accounts(rpc, &payer.pubkey()).await?;
+    info!("🧹 Found {} empty token accounts", empty.len());
+    Ok(close_accounts(rpc, payer, &empty).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_is_closable() {
+        assert!(is_closable(&json!({ "tokenAmount": { "amount": "0" } })));
+        assert!(!is_closable(&json!({ "tokenAmount": { "amount": "15" } })));
+        assert!(!is_closable(&json!({})));
+        assert!(!is_closable(&json!({
+            "tokenAmount": { "amount": "0" },
+            "extensions": [{ "extension": "transferFeeAmount", "state": { "withheldAmount": 42 } }]
+        })));
+        assert!(is_closable(&json!({
+            "tokenAmount": { "amount": "0" },
+            "extensions": [{ "extension": "transferFeeAmount", "state": { "withheldAmount": 0 } }]
+        })));
+    }
+}
@@ -3,9 +3,18 @@ use teloxide::{
     types::Message,
     Bot,
 };
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
+use solana_sdk::pubkey::Pubkey;
 use crate::profit_db::ProfitDatabase;
+use crate::sell::SellAmount;
+use crate::wallets::WalletPool;
+use crate::governor::RiskGovernor;
+use crate::lists::{ListKind, Lists};
+use crate::Config;
 use tracing::{info, error};
 use anyhow::Result;
 
@@ -13,8 +22,13 @@ pub struct TelegramController {
     bot: Bot,
     profit_db: Arc<Mutex<ProfitDatabase>>,
     authorized_users: Vec<String>,
-    is_running: Arc<Mutex<bool>>,
+    governor: Arc<RiskGovernor>,
+    lists: Arc<Lists>,
     notification_chat_id: Option<String>,
+    cfg: Config,
+    wallets: Arc<WalletPool>,
+    /// When each user asked for `/sell_all`, awaiting their `/sell_all confirm`
+    sell_all_requests: Arc<std::sync::Mutex<HashMap<String, Instant>>>,
 }
 
 impl TelegramController {
@@ -23,17 +37,22 @@ impl TelegramController {
         profit_db: ProfitDatabase,
         authorized_users: Vec<String>,
         notification_chat_id: Option<String>,
+        cfg: Config,
+        services: crate::Services,
     ) -> Self {
         let bot = Bot::new(bot_token);
         let profit_db = Arc::new(Mutex::new(profit_db));
-        let is_running = Arc::new(Mutex::new(true));
 
         Self {
             bot,
             profit_db,
             authorized_users,
-            is_running,
+            governor: services.governor,
+            lists: services.lists,
             notification_chat_id,
+            cfg,
+            wallets: services.wallets,
+            sell_all_requests: Arc::new(std::sync::Mutex::new(HashMap::new())),
         }
     }
 
@@ -126,6 +145,12 @@ impl TelegramController {
             "/status" | "status" => {
                 self.send_status(msg.chat.id).await;
             }
+            "/paper" | "paper" => {
+                self.send_paper_info(msg.chat.id).await;
+            }
+            "/wallets" | "wallets" => {
+                self.send_wallets_info(msg.chat.id).await;
+            }
             "/reset" | "reset" => {
                 // Check authorization for reset command
                 if !self.is_authorized(&user_id) {
@@ -148,7 +173,7 @@ impl TelegramController {
                 }
                 self.stop_bot(msg.chat.id).await;
             }
-            "/start_bot" | "start_bot" => {
+            "/start_bot" | "start_bot" | "/resume" | "resume" => {
                 // Check authorization for start_bot command
                 if !self.is_authorized(&user_id) {
                     let response = "❌ Unauthorized access. Only authorized users can start the bot.";
@@ -159,6 +184,40 @@ impl TelegramController {
                 }
                 self.start_bot(msg.chat.id).await;
             }
+            "/lists" | "lists" => {
+                self.send_lists(msg.chat.id).await;
+            }
+            cmd if ["/block", "/unblock", "/trust", "/untrust"].iter().any(|c| cmd == *c || cmd.starts_with(&format!("{} ", c))) => {
+                if !self.is_authorized(&user_id) {
+                    let response = "❌ Unauthorized access. Only authorized users can edit the lists.";
+                    if let Err(e) = self.bot.send_message(msg.chat.id, response).await {
+                        error!("Failed to send unauthorized message: {}", e);
+                    }
+                    return;
+                }
+                let added_by = if username.is_empty() { user_id.clone() } else { format!("@{}", username) };
+                self.edit_lists(msg.chat.id, text.trim(), &added_by).await;
+            }
+            "/sell_all" | "/sell_all confirm" => {
+                if !self.is_authorized(&user_id) {
+                    let response = "❌ Unauthorized access. Only authorized users can sell.";
+                    if let Err(e) = self.bot.send_message(msg.chat.id, response).await {
+                        error!("Failed to send unauthorized message: {}", e);
+                    }
+                    return;
+                }
+                self.sell_all(msg.chat.id, &user_id, text.to_lowercase().trim() == "/sell_all confirm").await;
+            }
+            cmd if cmd == "/sell" || cmd.starts_with("/sell ") => {
+                if !self.is_authorized(&user_id) {
+                    let response = "❌ Unauthorized access. Only authorized users can sell.";
+                    if let Err(e) = self.bot.send_message(msg.chat.id, response).await {
+                        error!("Failed to send unauthorized message: {}", e);
+                    }
+                    return;
+                }
+                self.manual_sell(msg.chat.id, text.trim()).await;
+            }
             _ => {
                 let response = "❓ Unknown command. Use /help to see available commands.";
                 if let Err(e) = self.bot.send_message(msg.chat.id, response).await {
@@ -180,11 +239,19 @@ impl TelegramController {
 • `/help` - Show this help message
 • `/status` - Show bot status
 • `/profit` - Show current profit statistics
+• `/paper` - Show paper trading results
+• `/wallets` - Show balance and PnL per wallet
+• `/lists` - Show blocked mints and blocked/trusted creators
 
 **Authorized Commands (Admin only):**
 • `/reset` - Reset all profit data to zero
 • `/stop` - Stop the bot (pause trading)
-• `/start_bot` - Start the bot (resume trading)
+• `/start_bot` or `/resume` - Resume buying, also after a loss limit pause
+• `/sell <mint> [all|50%|amount]` - Sell all or part of a position
+• `/sell_all` - Emergency: pause buys and sell every token in every wallet (asks to confirm)
+• `/block mint|creator <address> [reason]` - Never buy this mint or creator
+• `/unblock mint|creator <address>` - Remove a block
+• `/trust <creator> [reason]` / `/untrust <creator>` - Manage trusted creators
 
 **Usage:**
 Send any of these commands to interact with the bot.
@@ -219,6 +286,21 @@ Some commands require authorization.
         }
     }
 
+    /// Send paper trading results
+    async fn send_paper_info(&self, chat_id: ChatId) {
+        let profit_db = self.profit_db.lock().await;
+        let response = match profit_db.get_paper_summary() {
+            Ok(summary) => {
+                let mode = if self.cfg.is_paper() { "PAPER" } else { "LIVE" };
+                format!("{}\n\nCurrent mode: {}", summary, mode)
+            }
+            Err(e) => format!("❌ Error getting paper trading data: {}", e),
+        };
+        if let Err(e) = self.bot.send_message(chat_id, response).await {
+            error!("Failed to send paper info: {}", e);
+        }
+    }
+
     /// Reset profit data
     async fn reset_profit(&self, chat_id: ChatId) {
         let profit_db = self.profit_db.lock().await;
@@ -245,10 +327,9 @@ Some commands require authorization.
 
     /// Stop the bot
     async fn stop_bot(&self, chat_id: ChatId) {
-        let mut is_running = self.is_running.lock().await;
-        *is_running = false;
+        self.governor.pause();
         
-        let response = "🛑 **Bot stopped successfully!**\n\nThe bot has been paused and will not execute new trades.\nUse `/start_bot` to resume trading.";
+        let response = "🛑 **Bot stopped successfully!**\n\nThe bot has been paused and will not execute new trades.\nOpen positions are still managed.\nUse `/start_bot` to resume trading.";
         if let Err(e) = self.bot.send_message(chat_id, response).await {
             error!("Failed to send stop confirmation: {}", e);
         }
@@ -261,8 +342,7 @@ Some commands require authorization.
 
     /// Start the bot
     async fn start_bot(&self, chat_id: ChatId) {
-        let mut is_running = self.is_running.lock().await;
-        *is_running = true;
+        self.governor.resume();
         
         let response = "✅ **Bot started successfully!**\n\nThe bot is now active and will execute trades based on signals.";
         if let Err(e) = self.bot.send_message(chat_id, response).await {
@@ -275,12 +355,187 @@ Some commands require authorization.
         self.send_notification("✅ Bot started via Telegram command").await;
     }
 
+    /// Manually sell all or part of a position: `/sell <mint> [all|50%|amount]`
+    async fn manual_sell(&self, chat_id: ChatId, text: &str) {
+        let mut args = text.split_whitespace().skip(1);
+        let parsed = match (args.next(), args.next()) {
+            (Some(mint), amount) => Pubkey::from_str(mint)
+                .map_err(|e| anyhow::anyhow!("Invalid mint: {}", e))
+                .and_then(|mint| Ok((mint, amount.map(SellAmount::from_str).transpose()?.unwrap_or(SellAmount::All)))),
+            (None, _) => Err(anyhow::anyhow!("Usage: /sell <mint> [all|50%|amount]")),
+        };
+        let (mint, amount) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                if let Err(e) = self.bot.send_message(chat_id, format!("❌ {}", e)).await {
+                    error!("Failed to send sell usage: {}", e);
+                }
+                return;
+            }
+        };
+
+        if let Err(e) = self.bot.send_message(chat_id, format!("🔴 Selling {} of {}...", amount, mint)).await {
+            error!("Failed to send sell confirmation: {}", e);
+        }
+        info!("🔴 Manual sell of {} ({}) via Telegram command", mint, amount);
+
+        let db = self.profit_db.lock().await.clone();
+        let results = crate::sell::execute_all_wallets(mint, amount, self.cfg.clone(), &self.wallets, db).await;
+        let lines: Vec<String> = results
+            .into_iter()
+            .filter_map(|(wallet, result)| match result {
+                Ok(outcome) if outcome.tokens_sold == 0 => None,
+                Ok(outcome) => Some(format!("✅ {}: sold {} tokens for {:+.4} SOL", wallet, outcome.tokens_sold, outcome.sol_received)),
+                Err(e) => Some(format!("❌ {}: sell failed: {}", wallet, e)),
+            })
+            .collect();
+        let response = if lines.is_empty() {
+            format!("ℹ️ No wallet holds {}", mint)
+        } else {
+            format!("🔴 Sell of {}\n{}", mint, lines.join("\n"))
+        };
+        if let Err(e) = self.bot.send_message(chat_id, response).await {
+            error!("Failed to send sell result: {}", e);
+        }
+    }
+
+    /// Emergency flatten: `/sell_all` asks, `/sell_all confirm` from the same user within `confirm_secs` does it
+    async fn sell_all(&self, chat_id: ChatId, user_id: &str, confirmed: bool) {
+        let window = Duration::from_secs(self.cfg.sell_all.confirm_secs);
+        let requested = {
+            let mut requests = self.sell_all_requests.lock().unwrap();
+            if confirmed {
+                requests.remove(user_id).is_some_and(|at| at.elapsed() <= window)
+            } else {
+                requests.insert(user_id.to_string(), Instant::now());
+                false
+            }
+        };
+        if !requested {
+            let response = if confirmed {
+                "❌ No pending sell-all. Send /sell_all first.".to_string()
+            } else {
+                format!(
+                    "⚠️ This pauses new buys and sells EVERY token in EVERY wallet with {} bps slippage.\nSend /sell_all confirm within {}s to go ahead.",
+                    crate::sell_all::aggressive(&self.cfg).slippage_bps, window.as_secs()
+                )
+            };
+            if let Err(e) = self.bot.send_message(chat_id, response).await {
+                error!("Failed to send sell-all prompt: {}", e);
+            }
+            return;
+        }
+
+        self.governor.pause();
+        info!("🚨 Sell-all confirmed via Telegram by {}", user_id);
+        if let Err(e) = self.bot.send_message(chat_id, "🚨 Buys paused, selling everything...").await {
+            error!("Failed to send sell-all confirmation: {}", e);
+        }
+        let db = self.profit_db.lock().await.clone();
+        let report = crate::sell_all::run(&self.cfg, &self.wallets, &db).await;
+        let summary = format!("{}\nBuys stay paused until /resume", report.summary());
+        if let Err(e) = self.bot.send_message(chat_id, summary).await {
+            error!("Failed to send sell-all result: {}", e);
+        }
+    }
+
+    /// Show the block and trust lists
+    async fn send_lists(&self, chat_id: ChatId) {
+        let data = self.lists.snapshot();
+        let mut lines = Vec::new();
+        for (kind, entries) in [
+            (ListKind::BlockedMint, &data.blocked_mints),
+            (ListKind::BlockedCreator, &data.blocked_creators),
+            (ListKind::TrustedCreator, &data.trusted_creators),
+        ] {
+            lines.push(format!("📋 {} ({}):", kind, entries.len()));
+            for entry in entries {
+                lines.push(format!("• {} by {}: {}", entry.address, entry.added_by, entry.reason));
+            }
+        }
+        if let Err(e) = self.bot.send_message(chat_id, lines.join("\n")).await {
+            error!("Failed to send lists: {}", e);
+        }
+    }
+
+    /// `/block mint|creator <address> [reason]`, `/unblock mint|creator <address>`,
+    /// `/trust <creator> [reason]` and `/untrust <creator>`
+    async fn edit_lists(&self, chat_id: ChatId, text: &str, added_by: &str) {
+        let mut args = text.split_whitespace();
+        let command = args.next().unwrap_or("").to_lowercase();
+        let kind = match command.as_str() {
+            "/trust" | "/untrust" => Some(ListKind::TrustedCreator),
+            _ => match args.next().map(str::to_lowercase).as_deref() {
+                Some("mint") => Some(ListKind::BlockedMint),
+                Some("creator") => Some(ListKind::BlockedCreator),
+                _ => None,
+            },
+        };
+        let address = args.next().map(Pubkey::from_str);
+        let reason = args.collect::<Vec<_>>().join(" ");
+        let response = match (kind, address) {
+            (Some(kind), Some(Ok(address))) => {
+                let result = if command.starts_with("/un") {
+                    self.lists.remove(kind, &address).map(|removed| {
+                        if removed {
+                            format!("✅ Removed {} from {}", address, kind)
+                        } else {
+                            format!("ℹ️ {} was not in {}", address, kind)
+                        }
+                    })
+                } else {
+                    self.lists.add(kind, &address, added_by, &reason).map(|added| {
+                        let verb = if added { "Added" } else { "Updated" };
+                        format!("✅ {} {} in {}", verb, address, kind)
+                    })
+                };
+                match result {
+                    Ok(message) => {
+                        info!("📋 {} ({})", message, added_by);
+                        message
+                    }
+                    Err(e) => format!("❌ Could not update the lists: {}", e),
+                }
+            }
+            (_, Some(Err(e))) => format!("❌ Invalid address: {}", e),
+            _ => "Usage: /block mint|creator <address> [reason], /unblock mint|creator <address>, /trust <creator> [reason], /untrust <creator>".to_string(),
+        };
+        if let Err(e) = self.bot.send_message(chat_id, response).await {
+            error!("Failed to send lists update: {}", e);
+        }
+    }
+
+    /// Balance and realized PnL for every wallet
+    async fn send_wallets_info(&self, chat_id: ChatId) {
+        let pnl = self.profit_db.lock().await.wallet_pnl().unwrap_or_else(|e| {
+            error!("Failed to load wallet PnL: {}", e);
+            Vec::new()
+        });
+        let mut lines = vec!["👛 Wallets:".to_string()];
+        for wallet in self.wallets.all() {
+            let balance = match crate::get_sol_balance(&self.cfg.rpc_http, &wallet.pubkey()).await {
+                Ok(balance) => format!("{:.4} SOL", balance),
+                Err(_) => "balance unavailable".to_string(),
+            };
+            let (profit, trades) = pnl
+                .iter()
+                .find(|(name, _, _)| name == &wallet.name)
+                .map_or((0.0, 0), |(_, profit, trades)| (*profit, *trades));
+            lines.push(format!("• {} ({}): {} | PnL {:+.4} SOL over {} trades", wallet.name, wallet.pubkey(), balance, profit, trades));
+        }
+        if let Err(e) = self.bot.send_message(chat_id, lines.join("\n")).await {
+            error!("Failed to send wallets info: {}", e);
+        }
+    }
+
     /// Send bot status
     async fn send_status(&self, chat_id: ChatId) {
-        let is_running = self.is_running.lock().await;
         let profit_db = self.profit_db.lock().await;
         
-        let status = if *is_running { "🟢 **ACTIVE**" } else { "🔴 **STOPPED**" };
+        let status = match self.governor.status() {
+            None => "🟢 **ACTIVE**".to_string(),
+            Some(halt) => format!("🔴 **STOPPED** ({})", halt),
+        };
         
         match profit_db.get_profit() {
             Ok(stats) => {
@@ -317,8 +572,7 @@ Some commands require authorization.
     /// Check if bot is running
     #[allow(dead_code)]
     pub async fn is_bot_running(&self) -> bool {
-        let is_running = self.is_running.lock().await;
-        *is_running
+        self.governor.status().is_none()
     }
 
     /// Get profit database reference
@@ -345,8 +599,12 @@ impl Clone for TelegramController {
             bot: self.bot.clone(),
             profit_db: self.profit_db.clone(),
             authorized_users: self.authorized_users.clone(),
-            is_running: self.is_running.clone(),
+            governor: self.governor.clone(),
+            lists: self.lists.clone(),
             notification_chat_id: self.notification_chat_id.clone(),
+            cfg: self.cfg.clone(),
+            wallets: self.wallets.clone(),
+            sell_all_requests: self.sell_all_requests.clone(),
         }
     }
 }
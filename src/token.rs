@@ -0,0 +1,271 @@
+use anyhow::{anyhow, Result};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+
+pub const TOKEN_PROGRAM: Pubkey = solana_sdk::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const TOKEN_2022_PROGRAM: Pubkey = solana_sdk::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+pub const ATA_PROGRAM: Pubkey = solana_sdk::pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+/// Size of a classic mint; Token-2022 extensions start after the padded account type byte
+const MINT_LEN: usize = 82;
+const ACCOUNT_TYPE_OFFSET: usize = 165;
+const EXTENSION_TYPE_TRANSFER_FEE_CONFIG: u16 = 1;
+
+/// Token-2022 mint extensions that can stop holders from selling
+pub const RESTRICTIVE_EXTENSIONS: &[(u16, &str)] = &[
+    (6, "DefaultAccountState"),
+    (9, "NonTransferable"),
+    (12, "PermanentDelegate"),
+    (14, "TransferHook"),
+    (26, "Pausable"),
+];
+
+/// One epoch's transfer fee parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferFee {
+    pub epoch: u64,
+    pub maximum_fee: u64,
+    pub basis_points: u16,
+}
+
+impl TransferFee {
+    /// Fee withheld when transferring `amount`, rounded up like the Token-2022 program
+    pub fn calculate(&self, amount: u64) -> u64 {
+        if self.basis_points == 0 || amount == 0 {
+            return 0;
+        }
+        let fee = (amount as u128 * self.basis_points as u128).div_ceil(10_000);
+        (fee as u64).min(self.maximum_fee)
+    }
+}
+
+/// Token-2022 `TransferFeeConfig` extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferFeeConfig {
+    pub older: TransferFee,
+    pub newer: TransferFee,
+}
+
+impl TransferFeeConfig {
+    pub fn fee_for(&self, epoch: u64, amount: u64) -> u64 {
+        if epoch >= self.newer.epoch {
+            self.newer.calculate(amount)
+        } else {
+            self.older.calculate(amount)
+        }
+    }
+}
+
+/// What we need to know about a mint to trade it
+#[derive(Debug, Clone)]
+pub struct MintInfo {
+    pub program: Pubkey,
+    pub decimals: u8,
+    pub supply: u64,
+    pub transfer_fee: Option<TransferFeeConfig>,
+    pub freeze_authority: Option<Pubkey>,
+    /// Token-2022 extension types present on the mint
+    pub extension_types: Vec<u16>,
+}
+
+impl MintInfo {
+    pub fn is_token_2022(&self) -> bool {
+        self.program == TOKEN_2022_PROGRAM
+    }
+
+    /// Transfer fee for `amount` in the given epoch (zero for classic mints)
+    pub fn transfer_fee(&self, epoch: u64, amount: u64) -> u64 {
+        self.transfer_fee.map_or(0, |fee| fee.fee_for(epoch, amount))
+    }
+
+    /// Names of any sell-blocking Token-2022 extensions on the mint
+    pub fn restrictive_extensions(&self) -> Vec<&'static str> {
+        RESTRICTIVE_EXTENSIONS
+            .iter()
+            .filter(|(ext_type, _)| self.extension_types.contains(ext_type))
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
+/// Iterate over the TLV extension entries of a Token-2022 mint or account
+pub fn extensions(data: &[u8]) -> impl Iterator<Item = (u16, &[u8])> + '_ {
+    let mut offset = ACCOUNT_TYPE_OFFSET + 1;
+    std::iter::from_fn(move || {
+        let header = data.get(offset..offset + 4)?;
+        let ext_type = u16::from_le_bytes([header[0], header[1]]);
+        let len = u16::from_le_bytes([header[2], header[3]]) as usize;
+        let value = data.get(offset + 4..offset + 4 + len)?;
+        offset += 4 + len;
+        // Type 0 marks uninitialized trailing space
+        (ext_type != 0).then_some((ext_type, value))
+    })
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn parse_transfer_fee(data: &[u8]) -> TransferFee {
+    TransferFee {
+        epoch: read_u64(data, 0),
+        maximum_fee: read_u64(data, 8),
+        basis_points: u16::from_le_bytes([data[16], data[17]]),
+    }
+}
+
+/// Parse the transfer fee extension out of raw Token-2022 mint data
+pub fn parse_transfer_fee_config(data: &[u8]) -> Option<TransferFeeConfig> {
+    let (_, value) = extensions(data).find(|(ext_type, _)| *ext_type == EXTENSION_TYPE_TRANSFER_FEE_CONFIG)?;
+    // Two authorities (32 + 32) and withheld amount (8) precede the fee schedule
+    if value.len() < 108 {
+        return None;
+    }
+    Some(TransferFeeConfig {
+        older: parse_transfer_fee(&value[72..90]),
+        newer: parse_transfer_fee(&value[90..108]),
+    })
+}
+
+/// Decode mint info from an account's owner and data
+pub fn parse_mint(owner: &Pubkey, data: &[u8]) -> Result<MintInfo> {
+    if *owner != TOKEN_PROGRAM && *owner != TOKEN_2022_PROGRAM {
+        return Err(anyhow!("Account is owned by {}, not a token program", owner));
+    }
+    if data.len() < MINT_LEN {
+        return Err(anyhow!("Mint data too short ({} bytes)", data.len()));
+    }
+    let is_2022 = *owner == TOKEN_2022_PROGRAM;
+    let transfer_fee = if is_2022 { parse_transfer_fee_config(data) } else { None };
+    let extension_types = if is_2022 { extensions(data).map(|(ext_type, _)| ext_type).collect() } else { Vec::new() };
+    // COption<Pubkey>: 4-byte tag then the key
+    let freeze_authority = (data[46] == 1).then(|| Pubkey::new_from_array(data[50..82].try_into().unwrap()));
+    Ok(MintInfo { program: *owner, decimals: data[44], supply: read_u64(data, 36), transfer_fee, freeze_authority, extension_types })
+}
+
+/// Fetch a mint and detect which token program owns it
+pub async fn fetch_mint(rpc: &RpcClient, mint: &Pubkey) -> Result<MintInfo> {
+    let account = rpc.get_account(mint).await?;
+    parse_mint(&account.owner, &account.data)
+}
+
+/// Associated token address for the given token program
+pub fn associated_token_address(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[owner.as_ref(), token_program.as_ref(), mint.as_ref()], &ATA_PROGRAM).0
+}
+
+/// Raw token balance of a token account under either program (0 if it doesn't exist)
+pub async fn token_balance(rpc: &RpcClient, token_account: &Pubkey) -> Result<u64> {
+    let account = rpc.get_account_with_commitment(token_account, rpc.commitment()).await?.value;
+    match account {
+        // Amount sits at the same offset for Token and Token-2022 accounts
+        Some(account) if account.data.len() >= 72 => Ok(read_u64(&account.data, 64)),
+        Some(_) => Err(anyhow!("Token account {} has unexpected data", token_account)),
+        None => Ok(0),
+    }
+}
+
+/// SPL `CloseAccount` instruction; works for both token programs
+pub fn close_account_instruction(token_program: &Pubkey, account: &Pubkey, destination: &Pubkey, owner: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: *token_program,
+        accounts: vec![
+            AccountMeta::new(*account, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+        data: vec![9],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fee_bytes(epoch: u64, maximum_fee: u64, basis_points: u16) -> Vec<u8> {
+        let mut out = epoch.to_le_bytes().to_vec();
+        out.extend_from_slice(&maximum_fee.to_le_bytes());
+        out.extend_from_slice(&basis_points.to_le_bytes());
+        out
+    }
+
+    fn token_2022_mint_with_fee(decimals: u8) -> Vec<u8> {
+        let mut data = vec![0u8; ACCOUNT_TYPE_OFFSET + 1];
+        data[44] = decimals;
+        data[45] = 1; // is_initialized
+        data[ACCOUNT_TYPE_OFFSET] = 1; // AccountType::Mint
+
+        // An unrelated extension first to exercise the TLV walk
+        data.extend_from_slice(&10u16.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.push(0);
+
+        let mut value = vec![0u8; 72];
+        value.extend(fee_bytes(500, 1_000, 100));
+        value.extend(fee_bytes(600, 5_000_000, 250));
+        data.extend_from_slice(&EXTENSION_TYPE_TRANSFER_FEE_CONFIG.to_le_bytes());
+        data.extend_from_slice(&(value.len() as u16).to_le_bytes());
+        data.extend(value);
+        data
+    }
+
+    #[test]
+    fn test_parse_token_2022_transfer_fee() {
+        let data = token_2022_mint_with_fee(6);
+        let info = parse_mint(&TOKEN_2022_PROGRAM, &data).unwrap();
+        assert!(info.is_token_2022());
+        assert_eq!(info.decimals, 6);
+
+        let fee = info.transfer_fee.unwrap();
+        assert_eq!(fee.older, TransferFee { epoch: 500, maximum_fee: 1_000, basis_points: 100 });
+        assert_eq!(fee.newer, TransferFee { epoch: 600, maximum_fee: 5_000_000, basis_points: 250 });
+
+        // Older schedule (1%) capped at 1_000
+        assert_eq!(info.transfer_fee(550, 50_000), 500);
+        assert_eq!(info.transfer_fee(550, 10_000_000), 1_000);
+        // Newer schedule (2.5%) rounds up
+        assert_eq!(info.transfer_fee(600, 1_001), 26);
+        assert_eq!(info.extension_types, vec![10, EXTENSION_TYPE_TRANSFER_FEE_CONFIG]);
+        assert!(info.restrictive_extensions().is_empty());
+    }
+
+    #[test]
+    fn test_freeze_authority_and_restrictive_extensions() {
+        let freezer = Pubkey::new_unique();
+        let mut data = token_2022_mint_with_fee(6);
+        data[46] = 1;
+        data[50..82].copy_from_slice(freezer.as_ref());
+        data.extend_from_slice(&14u16.to_le_bytes());
+        data.extend_from_slice(&64u16.to_le_bytes());
+        data.extend(vec![0u8; 64]);
+
+        let info = parse_mint(&TOKEN_2022_PROGRAM, &data).unwrap();
+        assert_eq!(info.freeze_authority, Some(freezer));
+        assert_eq!(info.restrictive_extensions(), vec!["TransferHook"]);
+    }
+
+    #[test]
+    fn test_classic_mint_has_no_fee() {
+        let mut data = vec![0u8; MINT_LEN];
+        data[36..44].copy_from_slice(&1_000_000_000u64.to_le_bytes());
+        data[44] = 9;
+        let info = parse_mint(&TOKEN_PROGRAM, &data).unwrap();
+        assert!(!info.is_token_2022());
+        assert_eq!(info.decimals, 9);
+        assert_eq!(info.supply, 1_000_000_000);
+        assert_eq!(info.transfer_fee(0, 1_000_000), 0);
+        assert_eq!(info.freeze_authority, None);
+        assert!(parse_mint(&Pubkey::new_unique(), &data).is_err());
+    }
+
+    #[test]
+    fn test_ata_differs_by_program() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        assert_ne!(
+            associated_token_address(&owner, &mint, &TOKEN_PROGRAM),
+            associated_token_address(&owner, &mint, &TOKEN_2022_PROGRAM)
+        );
+    }
+}
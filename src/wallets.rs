@@ -0,0 +1,216 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::{keypair::Keypair, Signer};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use crate::Config;
+
+/// Keypair used when no wallets are configured, preferring the encrypted keystore
+pub const DEFAULT_KEYSTORE: &str = "keys/id.keystore";
+pub const DEFAULT_KEYPAIR: &str = "keys/id.json";
+
+/// One configured trading wallet
+#[derive(Deserialize, Clone, Debug)]
+pub struct WalletEntry {
+    pub name: String,
+    pub keypair: String,
+}
+
+/// How buys are assigned to wallets
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WalletPolicy {
+    #[default]
+    RoundRobin,
+    LeastRecentlyUsed,
+    /// Spread each buy evenly across `split_count` wallets
+    Split,
+}
+
+/// Wallet settings (`[wallets]` table in config.toml)
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct WalletConfig {
+    pub accounts: Vec<WalletEntry>,
+    pub policy: WalletPolicy,
+    /// Discord channel id -> wallet name; pinned channels skip the policy
+    pub pins: HashMap<String, String>,
+    pub split_count: usize,
+}
+
+impl Default for WalletConfig {
+    fn default() -> Self {
+        Self {
+            accounts: Vec::new(),
+            policy: WalletPolicy::RoundRobin,
+            pins: HashMap::new(),
+            split_count: 2,
+        }
+    }
+}
+
+/// A named signing wallet
+#[derive(Clone)]
+pub struct Wallet {
+    pub name: String,
+    pub keypair: Arc<Keypair>,
+}
+
+impl Wallet {
+    pub fn new(name: impl Into<String>, keypair: Keypair) -> Self {
+        Self { name: name.into(), keypair: Arc::new(keypair) }
+    }
+
+    pub fn pubkey(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+}
+
+#[derive(Default)]
+struct Rotation {
+    next: usize,
+    tick: u64,
+    last_used: Vec<u64>,
+}
+
+/// All trading wallets plus the rotation state used to pick them
+pub struct WalletPool {
+    wallets: Vec<Wallet>,
+    config: WalletConfig,
+    rotation: Mutex<Rotation>,
+}
+
+impl WalletPool {
+    pub fn new(wallets: Vec<Wallet>, config: WalletConfig) -> Result<Self> {
+        if wallets.is_empty() {
+            return Err(anyhow!("No wallets configured"));
+        }
+        for (channel, name) in &config.pins {
+            if !wallets.iter().any(|w| &w.name == name) {
+                return Err(anyhow!("Channel {} is pinned to unknown wallet {}", channel, name));
+            }
+        }
+        let rotation = Rotation { last_used: vec![0; wallets.len()], ..Default::default() };
+        Ok(Self { wallets, config, rotation: Mutex::new(rotation) })
+    }
+
+    /// Load and unlock the configured wallets, or `keys/id.keystore` / `keys/id.json`
+    /// as "main" when none are listed
+    pub fn load(cfg: &Config) -> Result<Self> {
+        let entries = if cfg.wallets.accounts.is_empty() {
+            let default = if std::path::Path::new(DEFAULT_KEYSTORE).exists() { DEFAULT_KEYSTORE } else { DEFAULT_KEYPAIR };
+            vec![WalletEntry { name: "main".to_string(), keypair: default.to_string() }]
+        } else {
+            cfg.wallets.accounts.clone()
+        };
+        let mut unlocker = crate::keystore::Unlocker::default();
+        let wallets = entries
+            .into_iter()
+            .map(|entry| {
+                let keypair = unlocker
+                    .load(&entry.keypair)
+                    .map_err(|e| anyhow!("Could not load wallet {} from {}: {}", entry.name, entry.keypair, e))?;
+                Ok(Wallet::new(entry.name, keypair))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Self::new(wallets, cfg.wallets.clone())
+    }
+
+    pub fn all(&self) -> &[Wallet] {
+        &self.wallets
+    }
+
+    /// First configured wallet, used for maintenance work
+    pub fn primary(&self) -> &Wallet {
+        &self.wallets[0]
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Wallet> {
+        self.wallets.iter().find(|w| w.name == name)
+    }
+
+    /// Wallets that should take a buy from `source` (`discord:<channel>:<author>`)
+    pub fn select(&self, source: &str) -> Vec<Wallet> {
+        let mut rotation = self.rotation.lock().unwrap();
+        let channel = source.split(':').nth(1).unwrap_or(source);
+        let picked: Vec<usize> = match self.config.pins.get(channel) {
+            Some(name) => self.wallets.iter().position(|w| &w.name == name).into_iter().collect(),
+            None => match self.config.policy {
+                WalletPolicy::RoundRobin => {
+                    let i = rotation.next % self.wallets.len();
+                    rotation.next = i + 1;
+                    vec![i]
+                }
+                WalletPolicy::LeastRecentlyUsed => {
+                    let i = (0..self.wallets.len()).min_by_key(|&i| rotation.last_used[i]).unwrap_or(0);
+                    vec![i]
+                }
+                WalletPolicy::Split => {
+                    let n = self.config.split_count.clamp(1, self.wallets.len());
+                    let start = rotation.next;
+                    rotation.next = start + n;
+                    (start..start + n).map(|i| i % self.wallets.len()).collect()
+                }
+            },
+        };
+        for &i in &picked {
+            rotation.tick += 1;
+            rotation.last_used[i] = rotation.tick;
+        }
+        picked.into_iter().map(|i| self.wallets[i].clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(policy: WalletPolicy, pins: &[(&str, &str)]) -> WalletPool {
+        let wallets = ["a", "b", "c"].iter().map(|name| Wallet::new(*name, Keypair::new())).collect();
+        let config = WalletConfig {
+            policy,
+            pins: pins.iter().map(|(c, w)| (c.to_string(), w.to_string())).collect(),
+            split_count: 2,
+            ..Default::default()
+        };
+        WalletPool::new(wallets, config).unwrap()
+    }
+
+    fn names(wallets: Vec<Wallet>) -> Vec<String> {
+        wallets.into_iter().map(|w| w.name).collect()
+    }
+
+    #[test]
+    fn test_round_robin_and_pins() {
+        let pool = pool(WalletPolicy::RoundRobin, &[("42", "c")]);
+        assert_eq!(names(pool.select("discord:1:x")), vec!["a"]);
+        assert_eq!(names(pool.select("discord:42:x")), vec!["c"]);
+        assert_eq!(names(pool.select("discord:1:x")), vec!["b"]);
+        assert_eq!(names(pool.select("discord:1:x")), vec!["c"]);
+        assert_eq!(names(pool.select("discord:1:x")), vec!["a"]);
+    }
+
+    #[test]
+    fn test_least_recently_used() {
+        let pool = pool(WalletPolicy::LeastRecentlyUsed, &[("42", "a")]);
+        assert_eq!(names(pool.select("discord:42:x")), vec!["a"]);
+        assert_eq!(names(pool.select("discord:1:x")), vec!["b"]);
+        assert_eq!(names(pool.select("discord:1:x")), vec!["c"]);
+        assert_eq!(names(pool.select("discord:1:x")), vec!["a"]);
+    }
+
+    #[test]
+    fn test_split() {
+        let pool = pool(WalletPolicy::Split, &[]);
+        assert_eq!(names(pool.select("discord:1:x")), vec!["a", "b"]);
+        assert_eq!(names(pool.select("discord:1:x")), vec!["c", "a"]);
+    }
+
+    #[test]
+    fn test_unknown_pin_rejected() {
+        let wallets = vec![Wallet::new("a", Keypair::new())];
+        let config = WalletConfig { pins: HashMap::from([("1".to_string(), "zzz".to_string())]), ..Default::default() };
+        assert!(WalletPool::new(wallets, config).is_err());
+    }
+}